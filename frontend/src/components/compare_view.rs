@@ -1,33 +1,214 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use gloo_utils::document;
 use log::info;
+use models::report::{DistributionComparison, GatlingReport, Significance, TestrunData};
 use plotly::common::Title;
 use plotly::layout::Axis;
 use plotly::{Layout, Plot, Scatter};
+use gloo_net::http::Request;
+use models::TestrunDetails;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
 use yew::prelude::*;
-use yewdux::prelude::use_store;
+use yew_router::prelude::use_location;
+use yewdux::prelude::{use_store, Dispatch};
 
 use crate::store::CompareSelection;
 
-#[derive(Default, PartialEq)]
+#[derive(Default, PartialEq, Clone, Copy)]
 enum CriteriaSelection {
     #[default]
     Avg,
     Max,
     Min,
     P95,
+    Errors,
+    Throughput,
+}
+
+impl CriteriaSelection {
+    const ALL: [CriteriaSelection; 6] = [
+        CriteriaSelection::Avg,
+        CriteriaSelection::Min,
+        CriteriaSelection::Max,
+        CriteriaSelection::P95,
+        CriteriaSelection::Errors,
+        CriteriaSelection::Throughput,
+    ];
+
+    /// Name of the figure in the `metric` param of a compare link.
+    fn key(&self) -> &'static str {
+        match self {
+            CriteriaSelection::Avg => "avg",
+            CriteriaSelection::Min => "min",
+            CriteriaSelection::Max => "max",
+            CriteriaSelection::P95 => "p95",
+            CriteriaSelection::Errors => "errors",
+            CriteriaSelection::Throughput => "throughput",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.key() == key)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            CriteriaSelection::Avg => "Average",
+            CriteriaSelection::Min => "Minimum",
+            CriteriaSelection::Max => "Maximum",
+            CriteriaSelection::P95 => "Perc95",
+            CriteriaSelection::Errors => "Errors",
+            CriteriaSelection::Throughput => "Throughput",
+        }
+    }
+
+    fn unit(&self) -> &'static str {
+        match self {
+            CriteriaSelection::Errors => "Errors",
+            CriteriaSelection::Throughput => "Requests per second",
+            _ => "Milliseconds",
+        }
+    }
+
+    /// Throughput is the only figure where more is better.
+    fn higher_is_better(&self) -> bool {
+        *self == CriteriaSelection::Throughput
+    }
+
+    fn value(&self, report: &GatlingReport, request: &str) -> Option<f64> {
+        let r = report.request_stats.iter().find(|r| r.name == request)?;
+        Some(match self {
+            CriteriaSelection::Avg => r.avg as f64,
+            CriteriaSelection::Max => r.max as f64,
+            CriteriaSelection::Min => r.min as f64,
+            CriteriaSelection::P95 => r.p95 as f64,
+            CriteriaSelection::Errors => r.error_count() as f64,
+            CriteriaSelection::Throughput => r.throughput(report.duration()),
+        })
+    }
 }
 
 struct CompareData {
     request: String,
-    data: Vec<Option<u64>>,
+    data: Vec<Option<f64>>,
+    /// Distribution test of each run against the reference.
+    distributions: Vec<Option<DistributionComparison>>,
+    /// The largest relative change for the worse of a run compared with the
+    /// reference.
+    regression: Option<f64>,
+}
+
+fn distribution(
+    reference: &TestrunData,
+    run: &TestrunData,
+    request: &str,
+) -> Option<DistributionComparison> {
+    let histogram = |td: &TestrunData| {
+        td.statistics
+            .as_ref()?
+            .request_stats
+            .iter()
+            .find(|r| r.name == request)
+            .map(|r| r.histogram.clone())
+    };
+    histogram(reference)?.mann_whitney(&histogram(run)?)
+}
+
+pub fn significance_badge(comparison: &DistributionComparison) -> Html {
+    let class = match comparison.significance {
+        Significance::Slower => "slower",
+        Significance::Faster => "faster",
+        Significance::NoChange => "unchanged",
+    };
+    let title = format!(
+        "p = {:.4}, effect size {:+.2}",
+        comparison.p_value, comparison.effect_size
+    );
+    html!(<span class={classes!("badge", class)} title={title}>{ comparison.significance.name() }</span>)
+}
+
+fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// Like `format_value`, but always with a sign.
+fn format_delta(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{:+}", value)
+    } else {
+        format!("{:+.2}", value)
+    }
+}
+
+/// The change of `value` compared with `reference`, positive if it got
+/// worse. Any change of a zero reference counts as infinite.
+fn worsening(value: f64, reference: f64, criteria: CriteriaSelection) -> f64 {
+    let relative = if reference != 0.0 {
+        (value - reference) / reference
+    } else if value != reference {
+        (value - reference).signum() * f64::INFINITY
+    } else {
+        0.0
+    };
+    if criteria.higher_is_better() {
+        -relative
+    } else {
+        relative
+    }
+}
+
+fn delta_cell(
+    value: f64,
+    reference: f64,
+    criteria: CriteriaSelection,
+    distribution: Html,
+) -> Html {
+    let absolute = value - reference;
+    let class = match worsening(value, reference, criteria) {
+        w if w > 0.0 => Some("worse"),
+        w if w < 0.0 => Some("better"),
+        _ => None,
+    };
+    let relative = if reference != 0.0 {
+        format!("{:+.1}%", absolute / reference * 100.0)
+    } else {
+        "---".to_string()
+    };
+    html! {
+        <td class={classes!(class)} title={format_value(value)}>
+            { format!("{} ({})", format_delta(absolute), relative) }
+            { distribution }
+        </td>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct CompareViewProps {
+    /// The figure shown first, see `CriteriaSelection::key`.
+    #[prop_or_default]
+    pub metric: Option<String>,
 }
 
 #[function_component(CompareView)]
-pub fn compare() -> Html {
+pub fn compare(props: &CompareViewProps) -> Html {
     let (selection, _dispatch) = use_store::<CompareSelection>();
-    let criteria = use_state(|| CriteriaSelection::default());
+    let criteria = {
+        let metric = props.metric.clone();
+        use_state(move || {
+            metric
+                .as_deref()
+                .and_then(CriteriaSelection::from_key)
+                .unwrap_or_default()
+        })
+    };
+    let copied = use_state(|| false);
+    let deltas = use_state(|| false);
+    let sort_by_regression = use_state(|| false);
 
     let selection_for_plot = selection.clone();
     let criteria_for_plot = criteria.clone();
@@ -41,7 +222,7 @@ pub fn compare() -> Html {
             match selection_for_plot.testrun_data {
                 Some(ref tr) => {
                     let mut tr = tr.clone();
-                    tr.sort_by(|a, b| a.datum.cmp(&b.datum));
+                    tr.sort_by_key(|a| (a.sequence, a.start_date()));
 
                     let requests: HashSet<String> = tr
                         .iter()
@@ -59,26 +240,13 @@ pub fn compare() -> Html {
 
                     let x_axis: Vec<String> = tr
                         .iter()
-                        .map(|x| x.datum.map(|d| d.to_rfc3339()).unwrap_or("".to_string()))
+                        .map(|x| x.start_date().map(|d| d.to_rfc3339()).unwrap_or("".to_string()))
                         .collect();
 
                     for r in requests {
-                        let data: Vec<Option<u64>> = tr
+                        let data: Vec<Option<f64>> = tr
                             .iter()
-                            .map(|td| {
-                                td.statistics
-                                    .as_ref()
-                                    .unwrap()
-                                    .request_stats
-                                    .iter()
-                                    .find(|r2| &r2.name == &r)
-                                    .map(|r| match *criteria_for_plot {
-                                        CriteriaSelection::Avg => r.avg,
-                                        CriteriaSelection::Max => r.max,
-                                        CriteriaSelection::Min => r.min,
-                                        CriteriaSelection::P95 => r.p95,
-                                    })
-                            })
+                            .map(|td| criteria_for_plot.value(td.statistics.as_ref().unwrap(), &r))
                             .collect();
 
                         let trace = Scatter::new(x_axis.clone(), data).name(&r);
@@ -88,7 +256,7 @@ pub fn compare() -> Html {
                     let layout = Layout::new()
                         .title(Title::from("Speed graph"))
                         .x_axis(Axis::new().title(Title::from("Time")))
-                        .y_axis(Axis::new().title(Title::from("Milliseconds")));
+                        .y_axis(Axis::new().title(Title::from(criteria_for_plot.unit())));
                     plot.set_layout(layout);
 
                     info!("Trying to plot");
@@ -127,7 +295,10 @@ pub fn compare() -> Html {
     match selection.testrun_data {
         Some(ref tr) => {
             let mut tr = tr.clone();
-            tr.sort_by(|a, b| a.datum.cmp(&b.datum));
+            tr.sort_by_key(|a| (a.sequence, a.start_date()));
+            let runs = tr.iter().map(|x| x.id.clone()).collect::<Vec<_>>().join(",");
+            // The baseline is the reference if it is part of the selection.
+            let reference = tr.iter().position(|x| x.baseline).unwrap_or(0);
 
             let requests: HashSet<String> = tr
                 .iter()
@@ -143,35 +314,63 @@ pub fn compare() -> Html {
             let mut requests: Vec<String> = requests.into_iter().collect();
             requests.sort();
 
-            let data: Vec<_> = requests
+            let mut data: Vec<_> = requests
                 .iter()
-                .map(|r| CompareData {
-                    request: r.to_owned(),
-                    data: tr
+                .map(|r| {
+                    let data: Vec<Option<f64>> = tr
                         .iter()
-                        .map(|td| {
-                            td.statistics
-                                .as_ref()
-                                .unwrap()
-                                .request_stats
-                                .iter()
-                                .find(|r2| &r2.name == r)
-                                .map(|r| match *criteria {
-                                    CriteriaSelection::Avg => r.avg,
-                                    CriteriaSelection::Max => r.max,
-                                    CriteriaSelection::Min => r.min,
-                                    CriteriaSelection::P95 => r.p95,
-                                })
-                        })
-                        .collect(),
+                        .map(|td| criteria.value(td.statistics.as_ref().unwrap(), r))
+                        .collect();
+                    let regression = data[reference].and_then(|reference_value| {
+                        data.iter()
+                            .flatten()
+                            .map(|v| worsening(*v, reference_value, *criteria))
+                            .max_by(f64::total_cmp)
+                    });
+                    CompareData {
+                        request: r.to_owned(),
+                        data,
+                        distributions: tr
+                            .iter()
+                            .enumerate()
+                            .map(|(i, td)| if i == reference { None } else { distribution(&tr[reference], td, r) })
+                            .collect(),
+                        regression,
+                    }
                 })
                 .collect();
+            if *sort_by_regression {
+                data.sort_by(|a, b| {
+                    b.regression
+                        .unwrap_or(f64::NEG_INFINITY)
+                        .total_cmp(&a.regression.unwrap_or(f64::NEG_INFINITY))
+                });
+            }
+
+            let link = format!(
+                "{}/compare?runs={}&metric={}",
+                gloo_utils::window().location().origin().unwrap_or_default(),
+                runs,
+                criteria.key()
+            );
+            let copy_link = {
+                let copied = copied.clone();
+                let link = link.clone();
+                Callback::from(move |_| {
+                    let copied = copied.clone();
+                    let clipboard = gloo_utils::window().navigator().clipboard();
+                    let promise = clipboard.write_text(&link);
+                    spawn_local(async move {
+                        copied.set(JsFuture::from(promise).await.is_ok());
+                    });
+                })
+            };
 
-            let comparision_method = match *criteria {
-                CriteriaSelection::Avg => "Average",
-                CriteriaSelection::Min => "Minimum",
-                CriteriaSelection::Max => "Maximum",
-                CriteriaSelection::P95 => "Perc95",
+            let criteria_link = |c: CriteriaSelection, title: &str| {
+                let criteria = criteria.clone();
+                html! {
+                    <a onclick={Callback::from(move |_| criteria.set(c))}>{ format!("[{}]", title) }</a>
+                }
             };
 
             html! {
@@ -179,25 +378,29 @@ pub fn compare() -> Html {
                     <div style="float: right;">
 
                     <p>
-                        <a onclick={ let criteria = criteria.clone();
-                            Callback::from(move |_| {
-                            criteria.set(CriteriaSelection::Avg)
-                        })}>{ "[Avg]" }</a>
-                        <a onclick={ let criteria = criteria.clone();
-                            Callback::from(move |_| {
-                            criteria.set(CriteriaSelection::Min)
-                        })}>{ "[Min]" }</a>
-                        <a onclick={ let criteria = criteria.clone();
-                            Callback::from(move |_| {
-                            criteria.set(CriteriaSelection::Max)
-                        })}>{ "[Max]" }</a>
-                        <a onclick={ let criteria = criteria.clone();
-                            Callback::from(move |_| {
-                            criteria.set(CriteriaSelection::P95)
-                        })}>{ "[P95]" }</a>                    </p>
+                        { criteria_link(CriteriaSelection::Avg, "Avg") }
+                        { criteria_link(CriteriaSelection::Min, "Min") }
+                        { criteria_link(CriteriaSelection::Max, "Max") }
+                        { criteria_link(CriteriaSelection::P95, "P95") }
+                        { criteria_link(CriteriaSelection::Errors, "Errors") }
+                        { criteria_link(CriteriaSelection::Throughput, "Throughput") }
+                    </p>
+                    <p>
+                        <a onclick={ let deltas = deltas.clone(); Callback::from(move |_| deltas.set(!*deltas)) }>
+                            { if *deltas { "[Values]" } else { "[Deltas]" } }
+                        </a>
+                        <a onclick={ let sort = sort_by_regression.clone(); Callback::from(move |_| sort.set(!*sort)) }>
+                            { if *sort_by_regression { "[Sort by name]" } else { "[Sort by regression]" } }
+                        </a>
+                    </p>
 
                     </div>
-                    <h3>{ format!("Compare {}", comparision_method) }</h3>
+                    <h3>{ format!("Compare {}", criteria.name()) }</h3>
+                        <p>
+                            <a href={format!("/api/testruns/compare?runs={}&format=csv", runs)} class="pure-button">{ "CSV" }</a>
+                            <a href={format!("/api/testruns/compare?runs={}&format=json", runs)} class="pure-button" target="_blank">{ "JSON" }</a>
+                            <button class="pure-button" onclick={copy_link} title={link}>{ if *copied { "Link copied" } else { "Copy link" } }</button>
+                        </p>
                         <canvas ref={canvas_ref}></canvas>
                         <div id="plot-div"></div>
 
@@ -209,9 +412,12 @@ pub fn compare() -> Html {
                                 <tr>
                                     <th>{"Request"}</th>
                                     {
-                                        tr.iter().map(|x|
+                                        tr.iter().enumerate().map(|(i, x)|
                                             html!{
-                                                <th>{ &x.datum.map(|d| d.format("%Y-%m-%d %H:%M").to_string() ).unwrap_or("n/a".to_string()) }</th>
+                                                <th>
+                                                    { &x.start_date().map(|d| d.format("%Y-%m-%d %H:%M").to_string() ).unwrap_or("n/a".to_string()) }
+                                                    if i == reference { <span class="badge baseline">{ "reference" }</span> }
+                                                </th>
                                             }
                                         ).collect::<Html>()
                                     }
@@ -220,14 +426,17 @@ pub fn compare() -> Html {
                                 <tbody>
                                 {
                                     data.iter().map(|x| {
+                                        let reference_value = x.data[reference];
                                         html!{
                                             <tr>
                                                 <td>{ &x.request }</td>
                                                 {
-                                                    x.data.iter().map(|x| {
-                                                        match x {
-                                                            Some(v) => html!(<td>{ format!("{}", v) }</td>),
-                                                            None => html!(<td></td>)
+                                                    x.data.iter().zip(x.distributions.iter()).enumerate().map(|(i, (v, d))| {
+                                                        let badge = html! { for d.as_ref().map(significance_badge) };
+                                                        match (v, reference_value) {
+                                                            (Some(v), Some(r)) if *deltas && i != reference => delta_cell(*v, r, *criteria, badge),
+                                                            (Some(v), _) => html!(<td>{ format_value(*v) } { badge }</td>),
+                                                            (None, _) => html!(<td></td>)
                                                         }
                                                     }).collect::<Html>()
                                                 }
@@ -247,6 +456,68 @@ pub fn compare() -> Html {
     }
 }
 
+/// Compares the runs given in the URL, e.g. `/compare?runs=a,b,c&metric=p95`.
+#[function_component(SharedCompare)]
+pub fn shared_compare() -> Html {
+    // `runs` are comma separated testrun ids.
+    let query = use_location()
+        .and_then(|l| l.query::<HashMap<String, String>>().ok())
+        .unwrap_or_default();
+    let runs = query.get("runs").cloned().unwrap_or_default();
+    let metric = query.get("metric").cloned();
+    let error = use_state(|| None::<String>);
+
+    {
+        let error = error.clone();
+        use_effect_with(runs, move |runs| {
+            let runs: Vec<String> = runs
+                .split(',')
+                .map(str::trim)
+                .filter(|r| !r.is_empty())
+                .map(String::from)
+                .collect();
+            spawn_local(async move {
+                let mut testrun_data = vec![];
+                for run in runs {
+                    let resp = Request::get(&format!("/api/testruns/{}", run)).send().await;
+                    let details: Result<TestrunDetails, String> = match resp {
+                        Ok(resp) if resp.status() == 200 => {
+                            resp.json().await.map_err(|err| err.to_string())
+                        }
+                        Ok(resp) if resp.ok() => Err(format!("Testrun {} not found", run)),
+                        Ok(resp) => Err(format!(
+                            "Error fetching data {} ({})",
+                            resp.status(),
+                            resp.status_text()
+                        )),
+                        Err(err) => Err(err.to_string()),
+                    };
+                    match details.map(|d| d.testrun.data) {
+                        Ok(Some(data)) if data.statistics.is_some() => testrun_data.push(data),
+                        Ok(_) => {
+                            error.set(Some(format!("Testrun {} has no statistics", run)));
+                            return;
+                        }
+                        Err(err) => {
+                            error.set(Some(err));
+                            return;
+                        }
+                    }
+                }
+                Dispatch::<CompareSelection>::global().set(CompareSelection {
+                    testrun_data: Some(testrun_data),
+                });
+            });
+            || ()
+        });
+    }
+
+    match *error {
+        Some(ref err) => html! { <article>{ err }</article> },
+        None => html! { <CompareView {metric} /> },
+    }
+}
+
 /*
 pub fn draw_plotters(canvas: HtmlCanvasElement, pitch: f64, yaw: f64) -> Result<(), Box<dyn Error>> {
     let area = CanvasBackend::with_canvas_object(canvas)
@@ -16,6 +16,7 @@ pub enum Message {
 
 pub struct Ignition {
     description: NodeRef,
+    agents: NodeRef,
     message: Option<String>,
     properties: BTreeMap<String, NodeRef>,
     data: Option<AppConfig>,
@@ -28,6 +29,7 @@ impl Component for Ignition {
     fn create(ctx: &Context<Self>) -> Self {
         Ignition {
             description: NodeRef::default(),
+            agents: NodeRef::default(),
             properties: BTreeMap::new(),
             message: None,
             data: None,
@@ -45,6 +47,14 @@ impl Component for Ignition {
                     .parse()
                     .unwrap();
 
+                let agents = self
+                    .agents
+                    .cast::<HtmlInputElement>()
+                    .unwrap()
+                    .value()
+                    .parse()
+                    .unwrap_or(0);
+
                 let mut custom_params: HashMap<String, String> = HashMap::new();
                 for x in &self.properties {
                     let y: String =
@@ -59,6 +69,7 @@ impl Component for Ignition {
                     let body = RunTestParam {
                         description: description,
                         custom_params,
+                        agents,
                     };
                     let _ = Request::post("/api/run")
                         .json(&body)
@@ -117,6 +128,11 @@ impl Component for Ignition {
                         <label for="description">{"Description"}</label>
                         <input ref={self.description.clone()} id="description" class="pure-input-1-2" />
                     </div>
+                    <div class="pure-control-group">
+                        <label for="agents">{"Agents"}</label>
+                        <input ref={self.agents.clone()} id="agents" type="number" min="0" value="0" class="pure-input-1-4" />
+                        <span class="pure-form-message-inline">{ "0 runs the simulation locally" }</span>
+                    </div>
                     {
                         if let Some(ref d) = self.data {
                             self.properties.iter().map(|e|
@@ -0,0 +1,156 @@
+use futures::stream::{select, StreamExt};
+use gloo_net::eventsource::futures::EventSource;
+use models::LiveStats;
+use plotly::common::Title;
+use plotly::layout::Axis;
+use plotly::{Layout, Plot, Scatter};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew_router::prelude::Link;
+
+use crate::Route;
+
+/// Number of updates kept for the charts, about ten minutes.
+const HISTORY: usize = 300;
+
+#[derive(Properties, PartialEq)]
+pub struct LiveViewProps {
+    /// Name of the running testrun.
+    pub name: String,
+}
+
+async fn plot_history(history: &[LiveStats]) {
+    let x_axis: Vec<u64> = history.iter().map(|s| s.elapsed).collect();
+
+    let mut users = Plot::new();
+    users.add_trace(
+        Scatter::new(
+            x_axis.clone(),
+            history.iter().map(|s| s.active_users).collect(),
+        )
+        .name("active users"),
+    );
+    users.add_trace(
+        Scatter::new(x_axis.clone(), history.iter().map(|s| s.rps).collect())
+            .name("requests/s"),
+    );
+    users.set_layout(
+        Layout::new()
+            .title(Title::from("Load"))
+            .x_axis(Axis::new().title(Title::from("Seconds"))),
+    );
+    plotly::bindings::react("live-load", &users).await;
+
+    let mut latency = Plot::new();
+    latency.add_trace(
+        Scatter::new(x_axis, history.iter().map(|s| s.p95).collect()).name("p95"),
+    );
+    latency.set_layout(
+        Layout::new()
+            .title(Title::from("Response time"))
+            .x_axis(Axis::new().title(Title::from("Seconds")))
+            .y_axis(Axis::new().title(Title::from("Milliseconds"))),
+    );
+    plotly::bindings::react("live-latency", &latency).await;
+}
+
+/// Live figures and charts of a running testrun, updated by the server every
+/// few seconds.
+#[function_component(LiveView)]
+pub fn live_view(props: &LiveViewProps) -> Html {
+    let history = use_state(Vec::<LiveStats>::new);
+    let finished = use_state(|| None::<String>);
+
+    {
+        let history = history.clone();
+        let finished = finished.clone();
+        use_effect_with(props.name.clone(), move |name| {
+            let events = EventSource::new(&format!("/api/testruns/{}/live", name)).and_then(|mut es| {
+                let progress = es.subscribe("progress")?;
+                let done = es.subscribe("finished")?;
+                Ok((es, select(progress, done)))
+            });
+            let es = match events {
+                Ok((es, mut events)) => {
+                    spawn_local(async move {
+                        let mut stats = vec![];
+                        while let Some(Ok((event, message))) = events.next().await {
+                            let data = message.data().as_string().unwrap_or_default();
+                            if event == "finished" {
+                                finished.set(Some(data));
+                                break;
+                            }
+                            if let Ok(s) = serde_json::from_str::<LiveStats>(&data) {
+                                stats.push(s);
+                                if stats.len() > HISTORY {
+                                    stats.remove(0);
+                                }
+                                history.set(stats.clone());
+                            }
+                        }
+                    });
+                    Some(es)
+                }
+                Err(err) => {
+                    log::warn!("Cannot follow testrun: {}", err);
+                    None
+                }
+            };
+            // Dropping the event source closes it.
+            move || drop(es)
+        });
+    }
+
+    {
+        use_effect_with((*history).clone(), move |history| {
+            if !history.is_empty() {
+                let history = history.clone();
+                spawn_local(async move { plot_history(&history).await });
+            }
+            || ()
+        });
+    }
+
+    if let Some(ref id) = *finished {
+        return html! {
+            <article>
+                { "The testrun finished, see " }
+                <Link<Route> to={Route::Testrun { name: id.clone() }}>{ "its results" }</Link<Route>>
+                { "." }
+            </article>
+        };
+    }
+
+    let current = history.last().cloned().unwrap_or_default();
+    html! {
+        <article>
+            <h5>{ "Live" }</h5>
+            <table class="pure-table">
+                <thead>
+                    <tr>
+                        <th>{ "Elapsed" }</th><th>{ "Active users" }</th><th>{ "Started users" }</th>
+                        <th>{ "Requests" }</th><th>{ "Errors" }</th><th>{ "Requests/s" }</th><th>{ "P95" }</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    <tr>
+                        <td>{ format!("{}:{:02}", current.elapsed / 60, current.elapsed % 60) }</td>
+                        <td>{ current.active_users }</td>
+                        <td>{ current.started_users }</td>
+                        <td>{ current.requests }</td>
+                        <td>{ current.errors }</td>
+                        <td>{ format!("{:.1}", current.rps) }</td>
+                        <td>{ current.p95.map(|p| format!("{} ms", p)).unwrap_or("---".into()) }</td>
+                    </tr>
+                </tbody>
+            </table>
+            if history.is_empty() {
+                <p>{ "Waiting for the simulation to start..." }</p>
+            }
+            <div class="pure-g">
+                <div class="pure-u-1-2"><div id="live-load"></div></div>
+                <div class="pure-u-1-2"><div id="live-latency"></div></div>
+            </div>
+        </article>
+    }
+}
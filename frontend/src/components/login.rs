@@ -0,0 +1,67 @@
+use gloo_net::http::Request;
+use models::Login;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::Route;
+
+/// Login form of the UI, the server keeps the session in a cookie.
+#[function_component(LoginPage)]
+pub fn login_page() -> Html {
+    let navigator = use_navigator().unwrap();
+    let name = use_node_ref();
+    let password = use_node_ref();
+    let message = use_state(|| None::<String>);
+
+    let onsubmit = {
+        let name = name.clone();
+        let password = password.clone();
+        let message = message.clone();
+        Callback::from(move |ev: SubmitEvent| {
+            ev.prevent_default();
+            let login = Login {
+                name: name.cast::<HtmlInputElement>().unwrap().value(),
+                password: password.cast::<HtmlInputElement>().unwrap().value(),
+            };
+            let navigator = navigator.clone();
+            let message = message.clone();
+            spawn_local(async move {
+                let resp = Request::post("/api/login").json(&login).unwrap().send().await;
+                match resp {
+                    Ok(resp) if resp.ok() => navigator.push(&Route::Home),
+                    Ok(resp) if resp.status() == 401 => {
+                        message.set(Some("Wrong name or password".into()))
+                    }
+                    Ok(resp) => message.set(Some(format!(
+                        "Cannot log in {} ({})",
+                        resp.status(),
+                        resp.status_text()
+                    ))),
+                    Err(err) => message.set(Some(err.to_string())),
+                }
+            });
+        })
+    };
+
+    html! {
+        <main>
+            <div style="padding: 1em;">
+                <img src="/water-droplet.svg" width="40" style="float: left; padding: 1em;" />
+                <h1 class="h1">{ "Waterpistol" }</h1>
+            </div>
+            <article>
+                <h3>{ "Login" }</h3>
+                <form class="pure-form pure-form-stacked" {onsubmit}>
+                    <input type="text" placeholder="Name" ref={name} autofocus=true />
+                    <input type="password" placeholder="Password" ref={password} />
+                    if let Some(ref message) = *message {
+                        <p>{ message }</p>
+                    }
+                    <button type="submit" class="pure-button pure-button-primary">{ "Log in" }</button>
+                </form>
+            </article>
+        </main>
+    }
+}
@@ -1,8 +1,13 @@
 pub mod compare_view;
+pub mod live_view;
+pub mod login;
+pub mod testrun_detail;
+pub mod testrun_import;
 pub mod testrun_list;
 pub mod testrun_show;
 pub mod uploader;
 pub mod ignition;
 pub mod navigation;
 pub mod testsuite_list;
-pub mod status;
\ No newline at end of file
+pub mod status;
+pub mod trend_view;
\ No newline at end of file
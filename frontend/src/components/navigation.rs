@@ -1,3 +1,6 @@
+use gloo_net::http::Request;
+use models::CurrentUser;
+use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
@@ -11,8 +14,38 @@ pub fn navigation() -> Html {
     let navigator = use_navigator().unwrap();
     let onclick_testsuites = Callback::from(move |ev : MouseEvent| { ev.prevent_default(); navigator.push(&Route::Testsuites) });
 
+    let navigator = use_navigator().unwrap();
+    let onclick_trends = Callback::from(move |ev : MouseEvent| { ev.prevent_default(); navigator.push(&Route::Trends) });
+
     let navigator = use_navigator().unwrap();
     let onclick_status = Callback::from(move |ev : MouseEvent| { ev.prevent_default(); navigator.push(&Route::Status) });
+
+    // Sends to the login page as long as there is no valid session.
+    let user = use_state(|| None::<CurrentUser>);
+    {
+        let user = user.clone();
+        let navigator = use_navigator().unwrap();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                match Request::get("/api/me").send().await {
+                    Ok(resp) if resp.status() == 401 => navigator.push(&Route::Login),
+                    Ok(resp) => user.set(resp.json().await.ok()),
+                    Err(err) => log::warn!("Cannot fetch user: {}", err),
+                }
+            });
+            || ()
+        });
+    }
+
+    let navigator = use_navigator().unwrap();
+    let onclick_logout = Callback::from(move |ev : MouseEvent| {
+        ev.prevent_default();
+        let navigator = navigator.clone();
+        spawn_local(async move {
+            let _ = Request::post("/api/logout").send().await;
+            navigator.push(&Route::Login);
+        });
+    });
     html! {
         <div class="pure-menu pure-menu-horizontal">
         <ul class="pure-menu-list">
@@ -22,9 +55,17 @@ pub fn navigation() -> Html {
             <li class="pure-menu-item">
                 <a onclick={onclick_testsuites} href="#" class="pure-menu-link">{ "Testsuites" }</a>
             </li>
+            <li class="pure-menu-item">
+                <a onclick={onclick_trends} href="#" class="pure-menu-link">{ "Trends" }</a>
+            </li>
             <li class="pure-menu-item">
                 <a onclick={onclick_status} href="#" class="pure-menu-link">{ "Status" }</a>
             </li>
+            if let Some(user) = user.as_ref().filter(|u| u.auth_enabled) {
+                <li class="pure-menu-item">
+                    <a onclick={onclick_logout} href="#" class="pure-menu-link" title={ format!("Logged in as {}", user.name) }>{ "Logout" }</a>
+                </li>
+            }
         </ul>
         </div>
     }
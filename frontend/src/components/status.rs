@@ -1,5 +1,5 @@
 use gloo_net::http::Request;
-use models::{SystemStatus, SystemStatusResponse};
+use models::{AgentInfo, SystemStatus, SystemStatusResponse};
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
@@ -33,7 +33,35 @@ pub fn status() -> Html {
         });
     }
 
+    let agents = use_state(|| None);
+
+    {
+        let agents = agents.clone();
+        use_effect(move || {
+            if agents.is_none() {
+                spawn_local(async move {
+                    let resp = Request::get("/api/agents").send().await.unwrap();
+                    let result: Result<Vec<AgentInfo>, String> = {
+                        if !resp.ok() {
+                            Err(format!(
+                                "Error fetching data {} ({})",
+                                resp.status(),
+                                resp.status_text()
+                            ))
+                        } else {
+                            resp.json().await.map_err(|err| err.to_string())
+                        }
+                    };
+                    agents.set(Some(result));
+                });
+            }
+
+            || {}
+        });
+    }
+
     html! {
+        <>
         <article>
             <h3>{ "System Status" }</h3>
             {
@@ -69,5 +97,35 @@ pub fn status() -> Html {
             }
         }
         </article>
+        <article>
+            <h3>{ "Agents" }</h3>
+            <table class="pure-table">
+                <thead>
+                    <tr>
+                        <th>{ "Name" }</th>
+                        <th>{ "Last seen" }</th>
+                        <th>{ "State" }</th>
+                    </tr>
+                </thead>
+                <tbody>
+                {
+                    match agents.as_ref() {
+                        Some(Ok(agents)) => {
+                            agents.iter().map(|agent| {
+                                html! {
+                                    <tr>
+                                        <td>{ agent.name.clone() }</td>
+                                        <td>{ agent.last_seen.format("%Y-%m-%d %H:%M:%S").to_string() }</td>
+                                        <td>{ if agent.busy { "Busy" } else { "Idle" } }</td>
+                                    </tr>
+                                }
+                            }).collect::<Html>()},
+                        _ => { html!{} }
+                    }
+                }
+                </tbody>
+            </table>
+        </article>
+        </>
     }
 }
\ No newline at end of file
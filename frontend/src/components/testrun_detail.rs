@@ -0,0 +1,209 @@
+use gloo_net::http::Request;
+use models::TestrunDetails;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew_router::prelude::Link;
+
+use crate::components::compare_view::significance_badge;
+use crate::components::live_view::LiveView;
+use crate::components::testrun_show::{TestrunNotes, TestrunStats};
+use crate::Route;
+
+#[derive(Properties, PartialEq)]
+pub struct TestrunDetailProps {
+    pub name: String,
+}
+
+/// Full page of a single testrun, including the embedded gatling report.
+#[function_component(TestrunDetail)]
+pub fn testrun_detail(props: &TestrunDetailProps) -> Html {
+    let details = use_state(|| None::<Result<TestrunDetails, String>>);
+
+    {
+        let details = details.clone();
+        use_effect_with(props.name.clone(), move |name| {
+            let name = name.clone();
+            spawn_local(async move {
+                let resp = Request::get(&format!("/api/testruns/{}", name)).send().await;
+                let result = match resp {
+                    Ok(resp) if resp.status() == 200 => {
+                        resp.json().await.map_err(|err| err.to_string())
+                    }
+                    Ok(resp) if resp.ok() => Err(format!("Testrun {} not found", name)),
+                    Ok(resp) => Err(format!(
+                        "Error fetching data {} ({})",
+                        resp.status(),
+                        resp.status_text()
+                    )),
+                    Err(err) => Err(err.to_string()),
+                };
+                details.set(Some(result));
+            });
+            || ()
+        });
+    }
+
+    let details = match *details {
+        None => return html! { <article>{ "Loading..." }</article> },
+        Some(Err(ref err)) => return html! { <article>{ err }</article> },
+        Some(Ok(ref details)) => details,
+    };
+    let testrun = &details.testrun;
+    let Some(ref data) = testrun.data else {
+        return html! { <article>{ "No data for this testrun" }</article> };
+    };
+
+    let link = |title: &str, url: &Option<String>| match url {
+        Some(url) => html! {
+            <a href={url.clone()} class="pure-button" target="_blank">{ title }</a>
+        },
+        None => html!(),
+    };
+    let summary = data.statistics.as_ref().map(|s| s.summary()).unwrap_or_default();
+    let progress_text = testrun
+        .progress
+        .map(|p| format!(" ({} Users)", p))
+        .unwrap_or_default();
+
+    let mut params: Vec<_> = data.custom_params.iter().collect();
+    params.sort();
+
+    html! {
+        <>
+        <article>
+            <h3>
+                { data.statistics.as_ref().map(|s| s.name.clone()).unwrap_or(testrun.name.clone()) }
+                if data.baseline {
+                    <span class="badge baseline">{ "baseline" }</span>
+                }
+                if data.regression.as_ref().is_some_and(|r| r.regressed()) {
+                    <span class="badge regression">{ "regression" }</span>
+                }
+            </h3>
+            <div class="pure-g">
+                <div class="pure-u-1-2">
+                    <table class="pure-table">
+                        <tbody>
+                            <tr><td>{ "Run" }</td><td>{ format!("#{} ({})", data.sequence, testrun.id()) }</td></tr>
+                            <tr><td>{ "Started" }</td><td>{ data.start_date().map(|x| x.format("%Y-%m-%d %H:%M:%S").to_string()) }</td></tr>
+                            <tr><td>{ "Finished" }</td><td>{ data.finished_at.map(|x| x.format("%Y-%m-%d %H:%M:%S").to_string()) }</td></tr>
+                            <tr><td>{ "Status" }</td><td>{ format!("{:?}", data.status) }{ progress_text }</td></tr>
+                            <tr><td>{ "Requests" }</td><td>{ summary.requests }</td></tr>
+                            <tr><td>{ "Failure%" }</td><td>{ format!("{:.4}%", summary.error_rate * 100.0) }</td></tr>
+                            <tr><td>{ "Mean / P95 / Max" }</td><td>{ format!("{} / {} / {} ms", summary.mean, summary.p95, summary.max) }</td></tr>
+                            <tr><td>{ "Duration" }</td><td>{ format!("{} s", summary.duration / 1000) }</td></tr>
+                        </tbody>
+                    </table>
+                </div>
+                <div class="pure-u-1-2">
+                    <table class="pure-table">
+                        <thead>
+                            <tr><th>{ "Param" }</th><th>{ "Value" }</th></tr>
+                        </thead>
+                        <tbody>
+                        {
+                            params.iter().map(|(k, v)| html! {
+                                <tr><td>{ k }</td><td>{ v }</td></tr>
+                            }).collect::<Html>()
+                        }
+                        </tbody>
+                    </table>
+                </div>
+            </div>
+            <p>
+                { link("report", &details.report_url) }
+                { link("console log", &details.console_log_url) }
+                { link("simulation.log", &details.simulation_log_url) }
+                <a href={format!("/api/testruns/{}/export", testrun.name)} class="pure-button">{ "export" }</a>
+                <a href={format!("/api/testruns/{}/stats?format=csv", testrun.name)} class="pure-button">{ "stats CSV" }</a>
+                <a href={format!("/api/testruns/{}/stats?format=json", testrun.name)} class="pure-button" target="_blank">{ "stats JSON" }</a>
+                <a href={format!("/api/testruns/{}/junit.xml", testrun.name)} class="pure-button" target="_blank">{ "JUnit" }</a>
+            </p>
+            <TestrunNotes key={testrun.name.clone()} name={testrun.name.clone()} notes={data.notes.clone()} tags={data.tags.clone()} />
+        </article>
+        if testrun.name.starts_with("running-") {
+            <LiveView name={testrun.name.clone()} />
+        }
+        if let Some(ref regression) = data.regression {
+            <article>
+                <h5>
+                    { "Compared with baseline " }
+                    <Link<Route> to={Route::Testrun { name: regression.baseline.clone() }}>
+                        { format!("#{}", regression.baseline_sequence) }
+                    </Link<Route>>
+                </h5>
+                <table class="pure-table">
+                    <thead>
+                        <tr>
+                            <th>{ "Request" }</th><th>{ "Metric" }</th><th>{ "Baseline" }</th><th>{ "Value" }</th><th>{ "Delta" }</th><th>{ "Delta%" }</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                    {
+                        regression.deltas.iter().map(|d| html! {
+                            <tr class={classes!(d.regression.then_some("regressed"))}>
+                                <td>{ &d.request }</td>
+                                <td>{ d.metric.name() }</td>
+                                <td>{ d.baseline }</td>
+                                <td>{ d.value }</td>
+                                <td>{ format!("{:+}", d.absolute) }</td>
+                                <td>{ d.relative.map(|r| format!("{:+.1}%", r * 100.0)).unwrap_or("---".into()) }</td>
+                            </tr>
+                        }).collect::<Html>()
+                    }
+                    </tbody>
+                </table>
+                if !regression.distributions.is_empty() {
+                    <table class="pure-table">
+                        <thead>
+                            <tr>
+                                <th>{ "Request" }</th><th>{ "Effect size" }</th><th>{ "p" }</th><th>{ "Distribution" }</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                        {
+                            regression.distributions.iter().map(|d| html! {
+                                <tr>
+                                    <td>{ &d.request }</td>
+                                    <td>{ format!("{:+.2}", d.comparison.effect_size) }</td>
+                                    <td>{ format!("{:.4}", d.comparison.p_value) }</td>
+                                    <td>{ significance_badge(&d.comparison) }</td>
+                                </tr>
+                            }).collect::<Html>()
+                        }
+                        </tbody>
+                    </table>
+                }
+            </article>
+        }
+        if !details.hooks.is_empty() {
+            <article>
+                <h5>{ "Hooks" }</h5>
+                {
+                    details.hooks.iter().map(|h| html! {
+                        <div>
+                            <p>
+                                <code>{ &h.result.command }</code>
+                                { format!(" {:?}: exit code {}", h.result.kind, h.result.exit_code.map(|c| c.to_string()).unwrap_or("---".into())) }
+                                { " " }
+                                <a href={h.url.clone()} target="_blank">{ "log" }</a>
+                            </p>
+                            <pre class="hook-output">{ &h.output }</pre>
+                        </div>
+                    }).collect::<Html>()
+                }
+            </article>
+        }
+        if let Some(ref report) = data.statistics {
+            <article>
+                <TestrunStats report={report.clone()} />
+            </article>
+        }
+        if let Some(ref url) = details.report_url {
+            <article>
+                <iframe src={url.clone()} class="report"></iframe>
+            </article>
+        }
+        </>
+    }
+}
@@ -0,0 +1,63 @@
+use gloo::file::File;
+use gloo_net::http::Request;
+use web_sys::{js_sys, HtmlInputElement};
+use yew::prelude::*;
+
+/// Upload of a testrun archive exported by another waterpistol instance.
+#[function_component(TestrunImport)]
+pub fn testrun_import() -> Html {
+    let file_input = use_node_ref();
+    let message = use_state(String::new);
+
+    let onsubmit = {
+        let file_input = file_input.clone();
+        let message = message.clone();
+
+        Callback::from(move |ev: SubmitEvent| {
+            ev.prevent_default();
+
+            let input: HtmlInputElement = file_input.cast::<HtmlInputElement>().unwrap();
+            let Some(file) = input.files().and_then(|f| f.get(0)).map(File::from) else {
+                return;
+            };
+
+            let message = message.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                message.set("Importing...".into());
+                let data = gloo_file::futures::read_as_bytes(&file).await.unwrap();
+                let resp = Request::post("/api/testruns/import")
+                    .header("Content-Type", "application/gzip")
+                    .body(js_sys::Uint8Array::from(&data[..]))
+                    .unwrap()
+                    .send()
+                    .await;
+                match resp {
+                    Ok(resp) if resp.ok() => message.set("Import done".into()),
+                    Ok(resp) => message.set(resp.text().await.unwrap_or_default()),
+                    Err(err) => message.set(err.to_string()),
+                }
+            });
+        })
+    };
+
+    html! {
+        <article>
+            <h3>{"Import testrun"}</h3>
+            <form {onsubmit} class="pure-form pure-form-aligned">
+                <div class="pure-control-group">
+                    <label for="testrun-import">{"Archive"}</label>
+                    <input
+                    id="testrun-import"
+                    ref={file_input}
+                    type="file"
+                    accept="application/gzip"
+                />
+                </div>
+                <div class="pure-controls">
+                    <button type="submit" class="pure-button pure-button-primary">{ "Import" }</button>
+                    <span class="pure-form-message-inline">{ &*message }</span>
+                </div>
+            </form>
+        </article>
+    }
+}
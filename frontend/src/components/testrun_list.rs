@@ -1,45 +1,73 @@
 use std::collections::HashSet;
 use std::vec;
 
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::stream::{select_all, StreamExt};
+use gloo_net::eventsource::futures::EventSource;
 use gloo_net::http::Request;
-use models::UpdateTestrunData;
+use models::event::ServerEvent;
+use models::{
+    SortOrder, TestrunDetails, TestrunPage, TestrunQuery, TestrunSort, UpdateTestrunData,
+};
 use models::report::{TestrunStatus, TestrunVisibilityStatus};
 use models::{report::TestrunData, Testrun};
 use wasm_bindgen::prelude::*;
 use web_sys::HtmlInputElement;
 use yew::{html, Component, Html, Properties};
 use yew::{platform::spawn_local, prelude::*};
+use yew_router::prelude::Link;
 use yewdux::prelude::*;
 
 use crate::store::{CompareSelection, TestrunDataSelection};
+use crate::Route;
 
 pub enum Msg {
     Selected(Option<TestrunData>),
     Unselected(Option<TestrunData>),
     Clicked(TestrunDataSelection),
     ChangeVisibilityStatus(String, TestrunVisibilityStatus),
+    SetBaseline(String, bool),
+    Delete(String),
     Changed,
     Compare,
-    Data(Result<Vec<Testrun>, String>),
+    Data(Result<TestrunPage, String>),
+    More(Result<TestrunPage, String>),
+    Filter(TestrunQuery),
+    Sort(TestrunSort),
+    LoadMore,
     Refresh,
+    Event(ServerEvent),
+    /// A testrun which takes the place of the run with the given name, or is
+    /// added on top if the list does not contain it yet.
+    Replace(String, Testrun),
 }
 
+/// The events of `/api/events` which change the list.
+const RUN_EVENTS: [&str; 4] = ["run_queued", "run_progress", "run_finished", "run_failed"];
+
 #[derive(Properties, PartialEq)]
 pub struct Props {}
 
 pub struct TestrunList {
     pub data: Option<Result<Vec<Testrun>, String>>,
+    pub next_cursor: Option<String>,
+    pub query: TestrunQuery,
     pub selected_testruns: Vec<TestrunData>,
+    /// Closed when the list is dropped.
+    events: Option<EventSource>,
 }
 
 impl Component for TestrunList {
     type Message = Msg;
     type Properties = Props;
 
-    fn create(_ctx: &yew::Context<Self>) -> Self {
+    fn create(ctx: &yew::Context<Self>) -> Self {
         TestrunList {
             data: None,
+            next_cursor: None,
+            query: TestrunQuery::default(),
             selected_testruns: vec![],
+            events: subscribe_events(ctx),
         }
     }
     fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
@@ -50,22 +78,26 @@ impl Component for TestrunList {
             Some(Ok(ref data)) => {
                 let onclick = ctx.link().callback(|_| Msg::Compare);
                 let onclick2 = ctx.link().callback(|_| Msg::Refresh);
+                let load_more = ctx.link().callback(|_| Msg::LoadMore);
 
                 let columns : HashSet<String> = data.iter().filter_map(|d| d.data.clone()).map(|d| d.custom_params.keys().cloned().collect::<Vec<_>>() ).flatten().collect();
 
                 html! {
                     <article>
                         <h3>{"Testruns"}</h3>
+                        { self.filter_form(ctx) }
                         <div style="max-height: 400px; overflow: auto;">
                         <table class="pure-table">
                         <thead>
                         <tr>
                         <th></th>
-                        <th>{ "Date" }</th>
-                        <th>{ "Name" }</th>
+                        { self.sort_header(ctx, "Date", TestrunSort::Date) }
+                        { self.sort_header(ctx, "Name", TestrunSort::Simulation) }
                         <th>{ "Status" }</th>
-                        <th>{ "Requests" }</th>
-                        <th>{ "(Failure%)" }</th>
+                        { self.sort_header(ctx, "Requests", TestrunSort::Requests) }
+                        { self.sort_header(ctx, "(Failure%)", TestrunSort::ErrorRate) }
+                        { self.sort_header(ctx, "Mean", TestrunSort::Mean) }
+                        { self.sort_header(ctx, "P95", TestrunSort::P95) }
                         {
                             columns.iter().map(|c| html! {
                                 <th>{ c }</th>
@@ -80,8 +112,44 @@ impl Component for TestrunList {
                                 data.iter().map(|testrun| {
                                     let x = testrun.data.clone();
                                     let uid = testrun.name.clone();
-                                    let onclick = ctx.link().callback(move |_| Msg::Clicked(TestrunDataSelection { testrun_data: x.clone() }));
-                                    let hide = ctx.link().callback(move |_| Msg::ChangeVisibilityStatus(uid.clone(), TestrunVisibilityStatus::Hidden));
+                                    let name = Some(testrun.name.clone());
+                                    let onclick = ctx.link().callback(move |_| Msg::Clicked(TestrunDataSelection { name: name.clone(), testrun_data: x.clone() }));
+                                    let delete = {
+                                        let uid = uid.clone();
+                                        ctx.link().callback(move |_| Msg::Delete(uid.clone()))
+                                    };
+                                    let visibility_status = testrun.data.as_ref().map(|d| d.visibility_status.clone()).unwrap_or_default();
+                                    let visibility_button = |title: &'static str, status: TestrunVisibilityStatus| {
+                                        let uid = uid.clone();
+                                        let onclick = ctx.link().callback(move |_| Msg::ChangeVisibilityStatus(uid.clone(), status.clone()));
+                                        html! {
+                                            <button {onclick} class="button-xsmall pure-button">{ title }</button>
+                                        }
+                                    };
+                                    let visibility_buttons = match visibility_status {
+                                        TestrunVisibilityStatus::Regular => html! {
+                                            <>
+                                            { visibility_button("hide", TestrunVisibilityStatus::Hidden) }
+                                            { visibility_button("highlight", TestrunVisibilityStatus::Highlighted) }
+                                            </>
+                                        },
+                                        TestrunVisibilityStatus::Highlighted => html! {
+                                            <>
+                                            { visibility_button("hide", TestrunVisibilityStatus::Hidden) }
+                                            { visibility_button("unhighlight", TestrunVisibilityStatus::Regular) }
+                                            </>
+                                        },
+                                        TestrunVisibilityStatus::Hidden => visibility_button("unhide", TestrunVisibilityStatus::Regular),
+                                    };
+                                    let baseline = testrun.data.as_ref().is_some_and(|d| d.baseline);
+                                    let regressed = testrun.data.as_ref().and_then(|d| d.regression.as_ref()).is_some_and(|r| r.regressed());
+                                    let baseline_button = {
+                                        let uid = uid.clone();
+                                        let onclick = ctx.link().callback(move |_| Msg::SetBaseline(uid.clone(), !baseline));
+                                        html! {
+                                            <button {onclick} class="button-xsmall pure-button">{ if baseline { "unset baseline" } else { "baseline" } }</button>
+                                        }
+                                    };
                                     let x = testrun.data.clone();
                                     let onchange = ctx.link().callback(move |ev:Event| {
                                         let input = ev
@@ -98,18 +166,22 @@ impl Component for TestrunList {
                                     }
                                     );
 
-                                    let (total, nok_ratio) = if let Some(ref st) = testrun.data.as_ref().unwrap().statistics {
-                                        (st.requests_nok + st.requests_ok, st.requests_nok as f32 / (st.requests_nok as f32 +st.requests_ok as f32))
-                                    } else {
-                                        (0, 0.0f32)
-                                    };
+                                    let summary = testrun.data.as_ref().and_then(|d| d.statistics.as_ref()).map(|st| st.summary()).unwrap_or_default();
 
                                     let row_class = match testrun.data.as_ref().map(|e| &e.status) {
                                         Some(TestrunStatus::Running) => {
                                             "running"
                                         },
+                                        Some(TestrunStatus::Failed) => {
+                                            "failed"
+                                        },
                                         _ => ""
                                     };
+                                    let visibility_class = match visibility_status {
+                                        TestrunVisibilityStatus::Highlighted => "highlighted",
+                                        TestrunVisibilityStatus::Hidden => "hidden",
+                                        TestrunVisibilityStatus::Regular => "",
+                                    };
                                     let progress_text = if let Some(progress) = testrun.progress {
                                         format!(" ({} Users)", progress)
                                     } else {
@@ -117,15 +189,36 @@ impl Component for TestrunList {
                                     };
                                     html!{
 
-                                    <tr key={testrun.name.clone()} class={row_class}>
+                                    <tr key={testrun.name.clone()} class={classes!(row_class, visibility_class, regressed.then_some("regressed"))}>
                                         <td>
                                             <input type="checkbox" {onchange}/>
                                         </td>
-                                        <td>{ testrun.data.as_ref().and_then(|x| x.datum).map(|x| x.format("%Y-%m-%d %H:%M").to_string() ) }</td>
-                                        <td>{ testrun.data.as_ref().and_then(|x| x.statistics.as_ref()).map(|x| x.name.clone()).unwrap_or("---".into()) }</td>
+                                        <td>{ testrun.data.as_ref().and_then(|x| x.start_date()).map(|x| x.format("%Y-%m-%d %H:%M").to_string() ) }</td>
+                                        <td>
+                                            <Link<Route> to={Route::Testrun { name: testrun.name.clone() }}>
+                                                { testrun.data.as_ref().and_then(|x| x.statistics.as_ref()).map(|x| x.name.clone()).unwrap_or("---".into()) }
+                                            </Link<Route>>
+                                            if baseline {
+                                                <span class="badge baseline">{ "baseline" }</span>
+                                            }
+                                            if regressed {
+                                                <span class="badge regression">{ "regression" }</span>
+                                            }
+                                            {
+                                                testrun.data.iter().flat_map(|d| d.tags.iter()).map(|tag| {
+                                                    let query = TestrunQuery { tags: Some(tag.clone()), ..self.query.clone() };
+                                                    let onclick = ctx.link().callback(move |_| Msg::Filter(query.clone()));
+                                                    html! {
+                                                        <span class="tag" {onclick}>{ tag }</span>
+                                                    }
+                                                }).collect::<Html>()
+                                            }
+                                        </td>
                                         <td>{ format!("{:?}", testrun.data.as_ref().unwrap().status) } {progress_text}</td>
-                                        <td>{ format!("{}", total)}</td>
-                                        <td>{ format!("{:.4}%", nok_ratio*100.0)}</td>
+                                        <td>{ format!("{}", summary.requests)}</td>
+                                        <td>{ format!("{:.4}%", summary.error_rate*100.0)}</td>
+                                        <td>{ format!("{} ms", summary.mean)}</td>
+                                        <td>{ format!("{} ms", summary.p95)}</td>
                                         {
                                             columns.iter().map(|c| html!{
                                                <td>{ format!("{}", testrun.data.as_ref().unwrap().custom_params.get(c).unwrap_or(&"---".to_owned())) }</td>
@@ -133,7 +226,13 @@ impl Component for TestrunList {
                                         }
                                         <td>
                                             <button {onclick} class="pure-button">{ "show" }</button>
-                                            <button onclick={hide} class="button-xsmall pure-button">{ "hide" }</button>
+                                            { visibility_buttons }
+                                            if testrun.data.as_ref().is_some_and(|d| d.statistics.is_some()) {
+                                                { baseline_button }
+                                            }
+                                            if !matches!(testrun.data.as_ref().map(|d| &d.status), Some(TestrunStatus::Running)) {
+                                                <button onclick={delete} class="button-xsmall pure-button">{ "delete" }</button>
+                                            }
                                             <a href={format!("/simulations/{}/", testrun.name)} class="pure-button" target="_blank">{ "report" }</a>
                                         </td>
                                     </tr>
@@ -143,6 +242,9 @@ impl Component for TestrunList {
                         }
                         </tbody>
                         </table>
+                        if self.next_cursor.is_some() {
+                            <button onclick={load_more} class="pure-button">{ "Load more" }</button>
+                        }
                         </div>
                         <button {onclick} class="pure-button pure-button-primary">{ "Compare" }</button>
                         <button onclick={onclick2} class="pure-button pure-button-primary">{ "Refresh" }</button>
@@ -166,19 +268,96 @@ impl Component for TestrunList {
     fn update(&mut self, ctx: &yew::Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::Data(d) => {
-                self.data = Some(d);
+                self.next_cursor = d.as_ref().ok().and_then(|p| p.next_cursor.clone());
+                self.data = Some(d.map(|p| p.testruns));
+                true
+            }
+            Msg::More(Ok(page)) => {
+                self.next_cursor = page.next_cursor;
+                if let Some(Ok(ref mut data)) = self.data {
+                    data.extend(page.testruns);
+                }
+                true
+            }
+            Msg::More(Err(err)) => {
+                self.data = Some(Err(err));
+                true
+            }
+            Msg::Filter(query) => {
+                self.query = query;
+                self.update_list(ctx);
+                false
+            }
+            Msg::Sort(sort) => {
+                let order = if self.query.sort.unwrap_or_default() == sort {
+                    match self.query.order.unwrap_or_default() {
+                        SortOrder::Asc => SortOrder::Desc,
+                        SortOrder::Desc => SortOrder::Asc,
+                    }
+                } else {
+                    SortOrder::default()
+                };
+                self.query.sort = Some(sort);
+                self.query.order = Some(order);
+                self.update_list(ctx);
                 true
             }
+            Msg::LoadMore => {
+                if let Some(cursor) = self.next_cursor.clone() {
+                    let query = TestrunQuery {
+                        cursor: Some(cursor),
+                        ..self.query.clone()
+                    };
+                    let link = ctx.link().clone();
+                    spawn_local(async move {
+                        link.send_message(Msg::More(fetch_testruns(&query).await));
+                    });
+                }
+                false
+            }
             Msg::Refresh => {
                 self.update_list(ctx);
                 false
             }
+            Msg::Event(ServerEvent::RunProgress { id, stats }) => {
+                let name = format!("running-{}", id);
+                if let Some(Ok(ref mut data)) = self.data {
+                    if let Some(testrun) = data.iter_mut().find(|t| t.name == name) {
+                        testrun.progress = Some(stats.started_users);
+                        return true;
+                    }
+                }
+                false
+            }
+            Msg::Event(ServerEvent::RunQueued { id, .. }) => {
+                let name = format!("running-{}", id);
+                self.replace_run(ctx, name.clone(), name);
+                false
+            }
+            Msg::Event(ServerEvent::RunFinished { id, .. } | ServerEvent::RunFailed { id, .. }) => {
+                self.replace_run(ctx, format!("running-{}", id), id);
+                false
+            }
+            Msg::Event(_) => false,
+            Msg::Replace(name, testrun) => {
+                let Some(Ok(ref mut data)) = self.data else {
+                    return false;
+                };
+                match data
+                    .iter_mut()
+                    .find(|t| t.name == name || t.name == testrun.name)
+                {
+                    Some(existing) => *existing = testrun,
+                    None => data.insert(0, testrun),
+                }
+                true
+            }
             Msg::Selected(Some(d)) => {
                 self.selected_testruns.push(d);
                 true
             }
             Msg::Unselected(Some(d)) => {
-                self.selected_testruns.retain(|a| a.datum != d.datum);
+                self.selected_testruns.retain(|a| a.id != d.id);
                 true
             }
             Msg::Clicked(d) => {
@@ -197,6 +376,27 @@ impl Component for TestrunList {
                 self.update_visibility_status(ctx, &uid, status);
                 true
             },
+            Msg::SetBaseline(uid, baseline) => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let _ = Request::patch(&format!("/api/testruns/{uid}")).json(&UpdateTestrunData {
+                        baseline: Some(baseline),
+                        ..Default::default()
+                    }).unwrap().send().await;
+                    link.send_message(Msg::Refresh);
+                });
+                false
+            },
+            Msg::Delete(uid) => {
+                if gloo::dialogs::confirm(&format!("Delete testrun {} permanently?", uid)) {
+                    let link = ctx.link().clone();
+                    spawn_local(async move {
+                        let _ = Request::delete(&format!("/api/testruns/{uid}")).send().await;
+                        link.send_message(Msg::Refresh);
+                    });
+                }
+                false
+            }
             Msg::Changed => false,
             Msg::Selected(_) => false,
             Msg::Unselected(_) => false
@@ -215,21 +415,111 @@ impl Component for TestrunList {
 impl TestrunList {
     fn update_list(&mut self, ctx: &yew::Context<Self>) {
         let link = ctx.link().clone();
+        let query = self.query.clone();
         spawn_local(async move {
-            let resp = Request::get("/api/testruns").send().await.unwrap();
-            let result: Result<Vec<Testrun>, String> = {
-                if !resp.ok() {
-                    Err(format!(
-                        "Error fetching data {} ({})",
-                        resp.status(),
-                        resp.status_text()
-                    ))
-                } else {
-                    resp.json().await.map_err(|err| err.to_string())
-                }
-            };
-            link.send_message(Msg::Data(result));
+            link.send_message(Msg::Data(fetch_testruns(&query).await));
+        });
+    }
+
+    /// Fetches the testrun `name` to take the place of the run `replaces`,
+    /// so that the pages loaded so far are kept.
+    fn replace_run(&self, ctx: &yew::Context<Self>, replaces: String, name: String) {
+        let link = ctx.link().clone();
+        spawn_local(async move {
+            match fetch_testrun(&name).await {
+                Ok(testrun) => link.send_message(Msg::Replace(replaces, testrun)),
+                Err(err) => log::warn!("Cannot fetch testrun {}: {}", name, err),
+            }
+        });
+    }
+
+    fn sort_header(&self, ctx: &yew::Context<Self>, title: &str, sort: TestrunSort) -> Html {
+        let arrow = if self.query.sort.unwrap_or_default() == sort {
+            match self.query.order.unwrap_or_default() {
+                SortOrder::Asc => " ▲",
+                SortOrder::Desc => " ▼",
+            }
+        } else {
+            ""
+        };
+        let onclick = ctx.link().callback(move |_| Msg::Sort(sort));
+        html! {
+            <th {onclick} style="cursor: pointer;">{ title }{ arrow }</th>
+        }
+    }
+
+    fn filter_form(&self, ctx: &yew::Context<Self>) -> Html {
+        let filter = |update: fn(&mut TestrunQuery, String)| {
+            let query = self.query.clone();
+            ctx.link().callback(move |ev: Event| {
+                let value = ev
+                    .target()
+                    .unwrap()
+                    .unchecked_into::<HtmlInputElement>()
+                    .value();
+                let mut query = query.clone();
+                update(&mut query, value);
+                Msg::Filter(query)
+            })
+        };
+
+        let from = filter(|q, v| q.from = parse_day(&v, 0, 0, 0));
+        let to = filter(|q, v| q.to = parse_day(&v, 23, 59, 59));
+        let status = filter(|q, v| {
+            q.status = match v.as_str() {
+                "Running" => Some(TestrunStatus::Running),
+                "Done" => Some(TestrunStatus::Done),
+                "Failed" => Some(TestrunStatus::Failed),
+                _ => None,
+            }
+        });
+        let visibility = filter(|q, v| {
+            q.visibility = match v.as_str() {
+                "Regular" => Some(TestrunVisibilityStatus::Regular),
+                "Highlighted" => Some(TestrunVisibilityStatus::Highlighted),
+                "Hidden" => Some(TestrunVisibilityStatus::Hidden),
+                _ => None,
+            }
         });
+        let include_hidden = {
+            let query = self.query.clone();
+            ctx.link().callback(move |ev: Event| {
+                let input = ev.target().unwrap().unchecked_into::<HtmlInputElement>();
+                Msg::Filter(TestrunQuery {
+                    include_hidden: input.checked(),
+                    ..query.clone()
+                })
+            })
+        };
+        let simulation = filter(|q, v| q.simulation = Some(v).filter(|v| !v.is_empty()));
+        let params = filter(|q, v| q.params = Some(v).filter(|v| !v.is_empty()));
+        let tags = filter(|q, v| q.tags = Some(v).filter(|v| !v.is_empty()));
+
+        html! {
+            <form class="pure-form" onsubmit={|e: SubmitEvent| e.prevent_default()}>
+                <input type="date" title="From" onchange={from}/>
+                <input type="date" title="To" onchange={to}/>
+                <select onchange={status}>
+                    <option value="">{ "All states" }</option>
+                    <option>{ "Running" }</option>
+                    <option>{ "Done" }</option>
+                    <option>{ "Failed" }</option>
+                </select>
+                <select onchange={visibility}>
+                    <option value="">{ "Not hidden" }</option>
+                    <option>{ "Regular" }</option>
+                    <option>{ "Highlighted" }</option>
+                    <option>{ "Hidden" }</option>
+                </select>
+                <input type="text" placeholder="Simulation" onchange={simulation}/>
+                <input type="text" placeholder="NAME:VALUE,..." onchange={params}/>
+                <input type="text" placeholder="Tags" value={self.query.tags.clone().unwrap_or_default()} onchange={tags}/>
+                <label>
+                    <input type="checkbox" checked={self.query.include_hidden} onchange={include_hidden}/>
+                    { " show hidden" }
+                </label>
+            </form>
+        }
     }
 
     fn update_visibility_status(&mut self, ctx: &yew::Context<Self>, uid : &str, visibility_status : TestrunVisibilityStatus) {
@@ -237,10 +527,123 @@ impl TestrunList {
         let uid = String::from(uid);
         spawn_local(async move {
             let resp = Request::patch(&format!("/api/testruns/{uid}")).json(&UpdateTestrunData {
-                visibility_status: Some(visibility_status)
+                visibility_status: Some(visibility_status),
+                ..Default::default()
             }).unwrap().send().await.unwrap();
             let _ = resp.ok();
             link.send_message(Msg::Refresh);
         });
     }
 }
+
+/// Follows the run events of the server, so that new and finished runs show
+/// up without a refresh.
+fn subscribe_events(ctx: &yew::Context<TestrunList>) -> Option<EventSource> {
+    let subscribed = EventSource::new("/api/events").and_then(|mut es| {
+        let streams = RUN_EVENTS
+            .iter()
+            .map(|name| es.subscribe(*name))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((es, select_all(streams)))
+    });
+    match subscribed {
+        Ok((es, mut events)) => {
+            let link = ctx.link().clone();
+            spawn_local(async move {
+                while let Some(Ok((_, message))) = events.next().await {
+                    let data = message.data().as_string().unwrap_or_default();
+                    if let Ok(event) = serde_json::from_str::<ServerEvent>(&data) {
+                        link.send_message(Msg::Event(event));
+                    }
+                }
+            });
+            Some(es)
+        }
+        Err(err) => {
+            log::warn!("Cannot follow server events: {}", err);
+            None
+        }
+    }
+}
+
+async fn fetch_testruns(query: &TestrunQuery) -> Result<TestrunPage, String> {
+    let resp = Request::get("/api/testruns")
+        .query(query_pairs(query))
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    if !resp.ok() {
+        Err(format!(
+            "Error fetching data {} ({})",
+            resp.status(),
+            resp.status_text()
+        ))
+    } else {
+        resp.json().await.map_err(|err| err.to_string())
+    }
+}
+
+async fn fetch_testrun(name: &str) -> Result<Testrun, String> {
+    let resp = Request::get(&format!("/api/testruns/{}", name))
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    if resp.status() != 200 {
+        return Err(format!(
+            "Error fetching testrun {} ({})",
+            resp.status(),
+            resp.status_text()
+        ));
+    }
+    let details: TestrunDetails = resp.json().await.map_err(|err| err.to_string())?;
+    Ok(details.testrun)
+}
+
+fn query_pairs(query: &TestrunQuery) -> Vec<(&'static str, String)> {
+    let mut pairs = vec![];
+    if let Some(from) = query.from {
+        pairs.push(("from", from.to_rfc3339()));
+    }
+    if let Some(to) = query.to {
+        pairs.push(("to", to.to_rfc3339()));
+    }
+    if let Some(ref status) = query.status {
+        pairs.push(("status", format!("{:?}", status)));
+    }
+    if let Some(ref simulation) = query.simulation {
+        pairs.push(("simulation", simulation.clone()));
+    }
+    if let Some(ref params) = query.params {
+        pairs.push(("params", params.clone()));
+    }
+    if let Some(ref tags) = query.tags {
+        pairs.push(("tags", tags.clone()));
+    }
+    if let Some(ref visibility) = query.visibility {
+        pairs.push(("visibility", format!("{:?}", visibility)));
+    }
+    if query.include_hidden {
+        pairs.push(("include_hidden", "true".into()));
+    }
+    if let Some(sort) = query.sort {
+        pairs.push(("sort", format!("{:?}", sort)));
+    }
+    if let Some(order) = query.order {
+        pairs.push(("order", format!("{:?}", order)));
+    }
+    if let Some(limit) = query.limit {
+        pairs.push(("limit", limit.to_string()));
+    }
+    if let Some(ref cursor) = query.cursor {
+        pairs.push(("cursor", cursor.clone()));
+    }
+    pairs
+}
+
+/// Turns the value of a date input into a point in time on that day.
+fn parse_day(value: &str, hour: u32, min: u32, sec: u32) -> Option<DateTime<Utc>> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(hour, min, sec)
+        .map(|d| d.and_utc())
+}
@@ -1,7 +1,17 @@
+use std::collections::BTreeSet;
+
+use gloo_net::http::Request;
+use models::report::GatlingReport;
+use models::UpdateTestrunData;
+use pulldown_cmark::{html::push_html, Event, Parser, Tag};
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use yew::platform::spawn_local;
 use yew::prelude::*;
+use yew_router::prelude::Link;
 use yewdux::prelude::use_store;
 
 use crate::store::TestrunDataSelection;
+use crate::Route;
 
 #[function_component(TestrunShow)]
 pub fn testrun() -> Html {
@@ -12,67 +22,18 @@ pub fn testrun() -> Html {
             let s = tr.statistics.as_ref().unwrap();
             html! {
                 <article>
-                        <h5>{ format!("{}", s.name)}</h5>
-
-                        <div class="pure-g">
-
-                        <div class="pure-u-4-5">
-                            <table class="pure-table">
-                                <thead>
-                                <tr>
-                                    <th>{"Request"}</th>
-                                    <th>{"Count"}</th>
-                                    <th>{"Errors"}</th>
-                                    <th>{"Min"}</th>
-                                    <th>{"Max"}</th>
-                                    <th>{"Avg"}</th>
-                                    <th>{"P95"}</th>
-                                </tr>
-                                </thead>
-                                <tbody> {
-                                    s.request_stats.iter().map(|x| {
-                                        let errors : u64 = x.errors.iter().map(|x| x.count).sum();
-
-                                        html!{
-                                            <tr>
-                                            <td>{ format!("{}", x.name) }</td>
-                                            <td>{ format!("{}", x.count) }</td>
-                                            <HighlightedCell value={errors} warning_limit=0 error_limit=10 />
-                                            <HighlightedCell value={x.min} />
-                                            <HighlightedCell value={x.max} />
-                                            <HighlightedCell value={x.avg} />
-                                            <HighlightedCell value={x.p95} />
-                                            </tr>
-                                        }
-
-                                    }).collect::<Html>()
-                                }
-                                </tbody>
-                            </table>
-                        </div>
-                        <div class="pure-u-1-5">
-                        <table class="pure-table">
-                                <thead>
-                                <tr>
-                                    <th>{"Journey"}</th>
-                                    <th>{"Count"}</th>
-                                </tr>
-                                </thead>
-                                <tbody> {
-                                    s.user_stats.iter().map(|x| {
-                                        html!{
-                                            <tr>
-                                            <td>{ format!("{}", x.name) }</td>
-                                            <td>{ format!("{}", x.count) }</td>
-                                            </tr>
-                                        }
-
-                                    }).collect::<Html>()
-                                }
-                                </tbody>
-                            </table>
-                        </div>
-                    </div>
+                        <h5>
+                            { format!("{}", s.name)}
+                            if let Some(ref name) = selection.name {
+                                { " " }
+                                <Link<Route> to={Route::Testrun { name: name.clone() }}>{ "details" }</Link<Route>>
+                            }
+                        </h5>
+                        if let Some(ref name) = selection.name {
+                            <TestrunNotes key={name.clone()} name={name.clone()} notes={tr.notes.clone()} tags={tr.tags.clone()} />
+                        }
+
+                        <TestrunStats report={s.clone()} />
                     </article>
             }
         }
@@ -80,6 +41,78 @@ pub fn testrun() -> Html {
     }
 }
 
+#[derive(Properties, PartialEq)]
+pub struct TestrunStatsProps {
+    pub report: GatlingReport,
+}
+
+/// Request and journey statistics of a report.
+#[function_component(TestrunStats)]
+pub fn testrun_stats(props: &TestrunStatsProps) -> Html {
+    let s = &props.report;
+    html! {
+        <div class="pure-g">
+
+        <div class="pure-u-4-5">
+            <table class="pure-table">
+                <thead>
+                <tr>
+                    <th>{"Request"}</th>
+                    <th>{"Count"}</th>
+                    <th>{"Errors"}</th>
+                    <th>{"Min"}</th>
+                    <th>{"Max"}</th>
+                    <th>{"Avg"}</th>
+                    <th>{"P95"}</th>
+                </tr>
+                </thead>
+                <tbody> {
+                    s.request_stats.iter().map(|x| {
+                        let errors : u64 = x.errors.iter().map(|x| x.count).sum();
+
+                        html!{
+                            <tr>
+                            <td>{ format!("{}", x.name) }</td>
+                            <td>{ format!("{}", x.count) }</td>
+                            <HighlightedCell value={errors} warning_limit=0 error_limit=10 />
+                            <HighlightedCell value={x.min} />
+                            <HighlightedCell value={x.max} />
+                            <HighlightedCell value={x.avg} />
+                            <HighlightedCell value={x.p95} />
+                            </tr>
+                        }
+
+                    }).collect::<Html>()
+                }
+                </tbody>
+            </table>
+        </div>
+        <div class="pure-u-1-5">
+        <table class="pure-table">
+                <thead>
+                <tr>
+                    <th>{"Journey"}</th>
+                    <th>{"Count"}</th>
+                </tr>
+                </thead>
+                <tbody> {
+                    s.user_stats.iter().map(|x| {
+                        html!{
+                            <tr>
+                            <td>{ format!("{}", x.name) }</td>
+                            <td>{ format!("{}", x.count) }</td>
+                            </tr>
+                        }
+
+                    }).collect::<Html>()
+                }
+                </tbody>
+            </table>
+        </div>
+            </div>
+    }
+}
+
 #[derive(Properties, PartialEq)]
 pub struct HighlightedCellProps {
     pub value: u64,
@@ -102,3 +135,162 @@ pub fn hcell(props: &HighlightedCellProps) -> Html {
         <td class={classes!(error_class)}>{ format!("{}", props.value )}</td>
     }
 }
+
+#[derive(Properties, PartialEq)]
+pub struct TestrunNotesProps {
+    pub name: String,
+    pub notes: String,
+    pub tags: BTreeSet<String>,
+}
+
+/// Markdown notes and tags of a testrun, editable in place. The props are
+/// only the initial values, so the component needs a `key` per testrun.
+#[function_component(TestrunNotes)]
+pub fn testrun_notes(props: &TestrunNotesProps) -> Html {
+    let notes = use_state(|| props.notes.clone());
+    let tags = use_state(|| props.tags.clone());
+    let editing = use_state(|| false);
+    let notes_ref = use_node_ref();
+    let tags_ref = use_node_ref();
+
+    if !*editing {
+        let onclick = {
+            let editing = editing.clone();
+            Callback::from(move |_| editing.set(true))
+        };
+        return html! {
+            <div class="notes">
+                { tags.iter().map(|t| html! { <span class="tag">{ t }</span> }).collect::<Html>() }
+                { render_markdown(&notes) }
+                <button {onclick} class="button-xsmall pure-button">{ "edit notes" }</button>
+            </div>
+        };
+    }
+
+    let onsubmit = {
+        let editing = editing.clone();
+        let notes_state = notes.clone();
+        let tags_state = tags.clone();
+        let notes_ref = notes_ref.clone();
+        let tags_ref = tags_ref.clone();
+        let name = props.name.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let notes = notes_ref.cast::<HtmlTextAreaElement>().unwrap().value();
+            let tags: BTreeSet<String> = tags_ref
+                .cast::<HtmlInputElement>()
+                .unwrap()
+                .value()
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect();
+            let update = UpdateTestrunData {
+                notes: Some(notes.clone()),
+                tags: Some(tags.clone()),
+                ..Default::default()
+            };
+            let editing = editing.clone();
+            let notes_state = notes_state.clone();
+            let tags_state = tags_state.clone();
+            let name = name.clone();
+            spawn_local(async move {
+                let resp = Request::patch(&format!("/api/testruns/{name}"))
+                    .json(&update)
+                    .unwrap()
+                    .send()
+                    .await;
+                if resp.map(|r| r.ok()).unwrap_or(false) {
+                    notes_state.set(notes);
+                    tags_state.set(tags);
+                    editing.set(false);
+                }
+            });
+        })
+    };
+    let tags = tags.iter().cloned().collect::<Vec<_>>().join(", ");
+
+    html! {
+        <form {onsubmit} class="pure-form pure-form-stacked notes">
+            <textarea ref={notes_ref} placeholder="Notes (markdown)" value={(*notes).clone()} />
+            <input ref={tags_ref} type="text" placeholder="tag-a, tag-b" value={tags} />
+            <button type="submit" class="pure-button pure-button-primary">{ "Save" }</button>
+        </form>
+    }
+}
+
+/// Renders markdown, leaving out raw HTML.
+fn render_markdown(markdown: &str) -> Html {
+    Html::from_html_unchecked(AttrValue::from(markdown_to_html(markdown)))
+}
+
+/// HTML of the markdown without raw HTML, links and images only keep http(s)
+/// and relative URLs, so that notes cannot run scripts.
+fn markdown_to_html(markdown: &str) -> String {
+    let parser = Parser::new(markdown)
+        .filter(|e| !matches!(e, Event::Html(_) | Event::InlineHtml(_)))
+        .map(|e| match e {
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) if !is_safe_url(&dest_url) => Event::Start(Tag::Link {
+                link_type,
+                dest_url: "".into(),
+                title,
+                id,
+            }),
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) if !is_safe_url(&dest_url) => Event::Start(Tag::Image {
+                link_type,
+                dest_url: "".into(),
+                title,
+                id,
+            }),
+            e => e,
+        });
+    let mut html = String::new();
+    push_html(&mut html, parser);
+    html
+}
+
+/// Whether the URL is relative or uses http or https. Browsers ignore
+/// whitespace and control characters in the scheme, so they are left out.
+fn is_safe_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => {
+            let scheme = url[..i].to_ascii_lowercase();
+            scheme == "http" || scheme == "https"
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::testrun_show::markdown_to_html;
+
+    #[test]
+    fn markdown_links_cannot_run_scripts() {
+        let html = markdown_to_html(
+            "[click](javascript:alert(1)) [entity](javascript&#58;alert(1)) \
+             ![img](JavaScript:alert(1)) <script>alert(1)</script>",
+        );
+        assert!(!html.to_lowercase().contains("javascript"), "{}", html);
+        assert!(!html.contains("<script>"), "{}", html);
+        assert!(html.contains(r#"<a href="">click</a>"#), "{}", html);
+
+        let html = markdown_to_html("[report](https://example.com/a) [run](/testruns/x)");
+        assert!(html.contains(r#"<a href="https://example.com/a">report</a>"#));
+        assert!(html.contains(r#"<a href="/testruns/x">run</a>"#));
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use gloo_net::http::Request;
+use models::config::ThresholdMetric;
+use models::trend::{Trend, TREND_METRICS};
+use plotly::common::{DashType, Fill, Line, Mode, Title};
+use plotly::layout::{Annotation, Axis};
+use plotly::{Layout, Plot, Scatter};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew_router::prelude::Link;
+
+use crate::Route;
+
+#[derive(Properties, PartialEq)]
+pub struct TrendViewProps {
+    #[prop_or_default]
+    pub simulation: Option<String>,
+}
+
+/// `NAME:VALUE` pairs as expected by the `params` filter.
+fn preset_filter(preset: &BTreeMap<String, String>) -> String {
+    preset
+        .iter()
+        .map(|(k, v)| format!("{}:{}", k, v))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn plot_id(index: usize) -> String {
+    format!("trend-plot-{}", index)
+}
+
+async fn plot_trend(trend: &Trend, metric: ThresholdMetric) {
+    let x_axis: Vec<String> = trend
+        .runs
+        .iter()
+        .map(|r| r.started_at.map(|d| d.to_rfc3339()).unwrap_or_default())
+        .collect();
+
+    for (i, request) in trend.requests.iter().enumerate() {
+        let Some(series) = request.series.iter().find(|s| s.metric == metric) else {
+            continue;
+        };
+
+        let mut plot = Plot::new();
+        plot.add_trace(
+            Scatter::new(x_axis.clone(), series.lower.clone())
+                .mode(Mode::Lines)
+                .line(Line::new().width(0.0))
+                .show_legend(false)
+                .name("Q1"),
+        );
+        plot.add_trace(
+            Scatter::new(x_axis.clone(), series.upper.clone())
+                .mode(Mode::Lines)
+                .line(Line::new().width(0.0))
+                .fill(Fill::ToNextY)
+                .fill_color("rgba(31, 141, 214, 0.2)")
+                .name("Q1 - Q3"),
+        );
+        plot.add_trace(
+            Scatter::new(x_axis.clone(), series.median.clone())
+                .mode(Mode::Lines)
+                .line(Line::new().dash(DashType::Dash).color("#1f8dd6"))
+                .name("rolling median"),
+        );
+        plot.add_trace(
+            Scatter::new(x_axis.clone(), series.values.clone())
+                .mode(Mode::LinesMarkers)
+                .connect_gaps(true)
+                .line(Line::new().color("#333"))
+                .name(metric.name()),
+        );
+
+        let mut layout = Layout::new()
+            .title(Title::from(request.request.as_str()))
+            .x_axis(Axis::new().title(Title::from("Time")))
+            .y_axis(Axis::new().title(Title::from(match metric {
+                ThresholdMetric::ErrorRate => "Error rate",
+                _ => "Milliseconds",
+            })));
+        for (run, (x, value)) in trend.runs.iter().zip(x_axis.iter().zip(&series.values)) {
+            let (Some(text), Some(value)) = (run.annotation(), value) else {
+                continue;
+            };
+            layout.add_annotation(
+                Annotation::new()
+                    .x(x.as_str())
+                    .y(*value)
+                    .text(format!("#{} {}", run.sequence, text))
+                    .show_arrow(true),
+            );
+        }
+        plot.set_layout(layout);
+
+        if gloo_utils::document().get_element_by_id(&plot_id(i)).is_some() {
+            plotly::bindings::new_plot(&plot_id(i), &plot).await;
+        }
+    }
+}
+
+/// Long-term trend of the requests of a simulation, with a rolling median
+/// band and the highlighted and annotated runs marked.
+#[function_component(TrendView)]
+pub fn trend_view(props: &TrendViewProps) -> Html {
+    let simulations = use_state(Vec::<String>::new);
+    let preset = use_state(|| None::<String>);
+    let metric = use_state(|| ThresholdMetric::P95);
+    let trend = use_state(|| None::<Result<Trend, String>>);
+
+    {
+        let simulations = simulations.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                if let Ok(resp) = Request::get("/api/trends").send().await {
+                    simulations.set(resp.json().await.unwrap_or_default());
+                }
+            });
+            || ()
+        });
+    }
+
+    {
+        let trend = trend.clone();
+        use_effect_with(
+            (props.simulation.clone(), (*preset).clone()),
+            move |(simulation, preset)| {
+                trend.set(None);
+                if let Some(simulation) = simulation.clone() {
+                    let preset = preset.clone();
+                    spawn_local(async move {
+                        let mut request = Request::get(&format!("/api/trends/{}", simulation));
+                        if let Some(ref preset) = preset {
+                            request = request.query([("params", preset)]);
+                        }
+                        let result = match request.send().await {
+                            Ok(resp) if resp.status() == 200 => {
+                                resp.json().await.map_err(|err| err.to_string())
+                            }
+                            Ok(resp) if resp.ok() => {
+                                Err(format!("No finished runs of {}", simulation))
+                            }
+                            Ok(resp) => Err(format!(
+                                "Error fetching data {} ({})",
+                                resp.status(),
+                                resp.status_text()
+                            )),
+                            Err(err) => Err(err.to_string()),
+                        };
+                        trend.set(Some(result));
+                    });
+                }
+                || ()
+            },
+        );
+    }
+
+    {
+        use_effect_with(((*trend).clone(), *metric), move |(trend, metric)| {
+            if let Some(Ok(trend)) = trend.clone() {
+                let metric = *metric;
+                spawn_local(async move { plot_trend(&trend, metric).await });
+            }
+            || ()
+        });
+    }
+
+    let simulation_links = simulations
+        .iter()
+        .map(|s| {
+            let class = classes!(
+                "pure-menu-item",
+                (props.simulation.as_ref() == Some(s)).then_some("pure-menu-selected")
+            );
+            html! {
+                <li {class}>
+                    <Link<Route> to={Route::Trend { simulation: s.clone() }} classes="pure-menu-link">{ s }</Link<Route>>
+                </li>
+            }
+        })
+        .collect::<Html>();
+
+    let content = match (&props.simulation, &*trend) {
+        (None, _) => html! { <p>{ "Choose a simulation." }</p> },
+        (Some(_), None) => html! { <p>{ "Loading..." }</p> },
+        (Some(_), Some(Err(err))) => html! { <p>{ err }</p> },
+        (Some(_), Some(Ok(trend))) => {
+            let preset_links = trend
+                .presets
+                .iter()
+                .filter(|p| !p.is_empty())
+                .map(|p| {
+                    let filter = preset_filter(p);
+                    let selected = preset.as_ref() == Some(&filter);
+                    let onclick = {
+                        let preset = preset.clone();
+                        let filter = filter.clone();
+                        Callback::from(move |_| preset.set(Some(filter.clone())))
+                    };
+                    html! {
+                        <a {onclick} class={classes!(selected.then_some("selected"))}>{ format!("[{}]", filter) }</a>
+                    }
+                })
+                .collect::<Html>();
+            let metric_links = TREND_METRICS
+                .into_iter()
+                .map(|m| {
+                    let onclick = {
+                        let metric = metric.clone();
+                        Callback::from(move |_| metric.set(m))
+                    };
+                    html! {
+                        <a {onclick} class={classes!((*metric == m).then_some("selected"))}>{ format!("[{}]", m.name()) }</a>
+                    }
+                })
+                .collect::<Html>();
+
+            html! {
+                <>
+                    <div style="float: right;">
+                        <p>{ metric_links }</p>
+                    </div>
+                    <h3>{ format!("Trend of {}", trend.simulation) }</h3>
+                    <p>
+                        { "Preset: " }
+                        <a onclick={ let preset = preset.clone(); Callback::from(move |_| preset.set(None)) }
+                            class={classes!(preset.is_none().then_some("selected"))}>{ "[all]" }</a>
+                        { preset_links }
+                    </p>
+                    <p>
+                        { format!("{} runs, the band shows the quartiles of the last {} runs around their median.", trend.runs.len(), trend.window) }
+                    </p>
+                    {
+                        (0..trend.requests.len()).map(|i| html! {
+                            <div id={plot_id(i)}></div>
+                        }).collect::<Html>()
+                    }
+                </>
+            }
+        }
+    };
+
+    html! {
+        <article>
+            <div class="pure-g">
+                <div class="pure-u-1-5">
+                    <div class="pure-menu">
+                        <span class="pure-menu-heading">{ "Simulations" }</span>
+                        <ul class="pure-menu-list">{ simulation_links }</ul>
+                    </div>
+                </div>
+                <div class="pure-u-4-5">{ content }</div>
+            </div>
+        </article>
+    }
+}
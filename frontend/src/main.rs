@@ -1,9 +1,13 @@
-use components::compare_view::CompareView;
+use components::compare_view::{CompareView, SharedCompare};
 use components::ignition::Ignition;
+use components::login::LoginPage;
 use components::status::Status;
+use components::testrun_detail::TestrunDetail;
+use components::testrun_import::TestrunImport;
 use components::testrun_list::TestrunList;
 use components::testrun_show::TestrunShow;
 use components::testsuite_list::TestsuiteList;
+use components::trend_view::TrendView;
 use components::uploader::Uploader;
 use components::navigation::Navigation;
 use yew::prelude::*;
@@ -20,12 +24,26 @@ pub enum Route {
     Testsuites,
     #[at("/status")]
     Status,
+    #[at("/testruns/:name")]
+    Testrun { name: String },
+    #[at("/compare")]
+    Compare,
+    #[at("/trends")]
+    Trends,
+    #[at("/trends/:simulation")]
+    Trend { simulation: String },
+    #[at("/login")]
+    Login,
     #[not_found]
     #[at("/404")]
     NotFound,
 }
 
 fn switch(routes: Route) -> Html {
+    if routes == Route::Login {
+        return html! { <LoginPage /> };
+    }
+
     html! {
             <main>
             <div class="pure-g">
@@ -60,13 +78,27 @@ fn switch(routes: Route) -> Html {
                     <>
                         <Uploader />
                         <TestsuiteList />
+                        <TestrunImport />
                     </>
                 },
+                Route::Testrun { name } => html! {
+                    <TestrunDetail {name} />
+                },
+                Route::Compare => html! {
+                    <SharedCompare />
+                },
+                Route::Trends => html! {
+                    <TrendView />
+                },
+                Route::Trend { simulation } => html! {
+                    <TrendView simulation={simulation} />
+                },
                 Route::Status => html! {
                     <>
                         <Status />
                     </>
                 },
+                Route::Login => html! {},
             }
         }
         </main>
@@ -1,12 +1,13 @@
 use models::report::TestrunData;
 use yewdux::store::Store;
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Store)]
+#[derive(Debug, Default, Clone, PartialEq, Store)]
 pub struct TestrunDataSelection {
+    pub name: Option<String>,
     pub testrun_data: Option<TestrunData>,
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Store)]
+#[derive(Debug, Default, Clone, PartialEq, Store)]
 pub struct CompareSelection {
     pub testrun_data: Option<Vec<TestrunData>>,
 }
@@ -1,8 +1,23 @@
 use serde::{Deserialize, Serialize};
 
+use crate::report::{TestrunData, TestrunStatus, Verdict};
+
 #[derive(Deserialize, Serialize, Default, Clone, PartialEq)]
 pub struct AppConfig {
     pub simulation: SimulationConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub thresholds: Vec<Threshold>,
+    #[serde(default)]
+    pub tolerances: Vec<Tolerance>,
+    /// URL the server is reachable at, used for the links in webhooks.
+    #[serde(default)]
+    pub public_url: Option<String>,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    #[serde(default)]
+    pub auth: AuthConfig,
 }
 
 impl AppConfig {
@@ -19,6 +34,8 @@ impl AppConfig {
 pub struct SimulationConfig {
     pub simulation_class: String,
     pub params: Vec<Param>,
+    #[serde(default)]
+    pub hooks: HookConfig,
 }
 
 #[derive(Deserialize, Serialize, Default, Clone, PartialEq)]
@@ -26,3 +43,245 @@ pub struct Param {
     pub name: String,
     pub value: String,
 }
+
+/// Shell commands that are run (via `bash -c`) before and after `mvn gatling:test`.
+///
+/// The parameters of the run are passed as environment variables. A failing
+/// `pre_run` command aborts the run.
+#[derive(Deserialize, Serialize, Default, Clone, PartialEq)]
+pub struct HookConfig {
+    pub pre_run: Option<String>,
+    pub post_run: Option<String>,
+}
+
+/// Upper limit for a figure of the requests of a run. Runs violating a
+/// threshold are reported as failed, e.g. in the JUnit report.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct Threshold {
+    /// Name of the request, the threshold applies to all requests if unset.
+    pub request: Option<String>,
+    pub metric: ThresholdMetric,
+    pub max: f64,
+}
+
+impl Threshold {
+    pub fn applies_to(&self, request: &str) -> bool {
+        self.request.as_ref().is_none_or(|r| r == request)
+    }
+}
+
+/// Allowed increase of a figure of the requests compared to the baseline run.
+/// A request regressed if its increase exceeds all limits given.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct Tolerance {
+    /// Name of the request, the tolerance applies to all requests if unset.
+    pub request: Option<String>,
+    pub metric: ThresholdMetric,
+    /// Increase relative to the baseline, `0.1` allows 10%.
+    pub relative: Option<f64>,
+    /// Increase in the unit of the metric.
+    pub absolute: Option<f64>,
+}
+
+impl Tolerance {
+    pub fn applies_to(&self, request: &str) -> bool {
+        self.request.as_ref().is_none_or(|r| r == request)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThresholdMetric {
+    Avg,
+    Max,
+    P50,
+    P95,
+    P99,
+    /// Number of KO responses.
+    Errors,
+    /// Share of KO responses, between 0 and 1.
+    ErrorRate,
+}
+
+impl ThresholdMetric {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThresholdMetric::Avg => "avg",
+            ThresholdMetric::Max => "max",
+            ThresholdMetric::P50 => "p50",
+            ThresholdMetric::P95 => "p95",
+            ThresholdMetric::P99 => "p99",
+            ThresholdMetric::Errors => "errors",
+            ThresholdMetric::ErrorRate => "error_rate",
+        }
+    }
+}
+
+/// Rules for cleaning up old testruns. Highlighted and running testruns are
+/// never touched, rules which are not set are not applied.
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+pub struct RetentionConfig {
+    /// Keep only the latest runs of every simulation.
+    pub keep_last: Option<usize>,
+    /// Delete runs older than this.
+    pub max_age_days: Option<u32>,
+    /// Remove the HTML report of runs older than this, keeping
+    /// `testrun-data.json`.
+    pub strip_reports_after_days: Option<u32>,
+    /// How often the rules are enforced.
+    #[serde(default = "default_retention_interval")]
+    pub interval_minutes: u64,
+}
+
+impl RetentionConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.keep_last.is_some()
+            || self.max_age_days.is_some()
+            || self.strip_reports_after_days.is_some()
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        RetentionConfig {
+            keep_last: None,
+            max_age_days: None,
+            strip_reports_after_days: None,
+            interval_minutes: default_retention_interval(),
+        }
+    }
+}
+
+fn default_retention_interval() -> u64 {
+    60
+}
+
+/// What a webhook is called for.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// A run finished with results, whether it passed or not.
+    Finished,
+    /// A run ended without results.
+    Failed,
+    /// A run finished and regressed compared to its baseline.
+    Regressed,
+}
+
+impl WebhookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::Finished => "finished",
+            WebhookEvent::Failed => "failed",
+            WebhookEvent::Regressed => "regressed",
+        }
+    }
+}
+
+/// A URL which gets a `WebhookPayload` posted when a run ends.
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// The events the webhook is called for, all by default.
+    #[serde(default = "default_webhook_events")]
+    pub events: Vec<WebhookEvent>,
+    /// Key of the HMAC-SHA256 signature of the body, sent in the
+    /// `X-Waterpistol-Signature` header. Never sent to clients.
+    #[serde(default, skip_serializing)]
+    pub secret: Option<String>,
+    /// Number of retries if the webhook cannot be reached or does not answer
+    /// with a success status.
+    #[serde(default = "default_webhook_retries")]
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every further one.
+    #[serde(default = "default_webhook_backoff")]
+    pub backoff_seconds: u64,
+}
+
+impl WebhookConfig {
+    /// The event the webhook is called with for a run which ended, the most
+    /// specific one it listens to. `None` if it does not listen to the run.
+    pub fn event_for(&self, data: &TestrunData, verdict: &Verdict) -> Option<WebhookEvent> {
+        let regressed = verdict.regression.as_ref().is_some_and(|r| r.regressed());
+        let events = match data.status {
+            TestrunStatus::Done if regressed => {
+                vec![WebhookEvent::Regressed, WebhookEvent::Finished]
+            }
+            TestrunStatus::Done => vec![WebhookEvent::Finished],
+            _ => vec![WebhookEvent::Failed],
+        };
+        events.into_iter().find(|e| self.events.contains(e))
+    }
+}
+
+fn default_webhook_events() -> Vec<WebhookEvent> {
+    vec![
+        WebhookEvent::Finished,
+        WebhookEvent::Failed,
+        WebhookEvent::Regressed,
+    ]
+}
+
+fn default_webhook_retries() -> u32 {
+    3
+}
+
+fn default_webhook_backoff() -> u64 {
+    2
+}
+
+/// Users and API tokens allowed to use the server. Without any of them,
+/// everybody has full access.
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub users: Vec<UserConfig>,
+    #[serde(default)]
+    pub tokens: Vec<TokenConfig>,
+    /// How long a login of the UI is valid.
+    #[serde(default = "default_session_hours")]
+    pub session_hours: u64,
+}
+
+impl AuthConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.users.is_empty() || !self.tokens.is_empty()
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            users: vec![],
+            tokens: vec![],
+            session_hours: default_session_hours(),
+        }
+    }
+}
+
+fn default_session_hours() -> u64 {
+    12
+}
+
+/// A user of the UI, see `waterpistol hash-password`.
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+pub struct UserConfig {
+    pub name: String,
+    /// Argon2 hash of the password in PHC format. Never sent to clients.
+    #[serde(default, skip_serializing)]
+    pub password_hash: String,
+    /// Read only users cannot start, change or upload anything.
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// A bearer token for CI and agents, see `waterpistol create-token`.
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+pub struct TokenConfig {
+    pub name: String,
+    /// Hex SHA-256 hash of the token. Never sent to clients.
+    #[serde(default, skip_serializing)]
+    pub token_hash: String,
+    #[serde(default)]
+    pub read_only: bool,
+}
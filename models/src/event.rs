@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::AppConfig;
+use crate::report::Verdict;
+use crate::LiveStats;
+
+/// Something that happened on the server, sent to the clients of
+/// `GET /api/events`. The name of the event is the `type` of the payload.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerEvent {
+    /// A testrun was requested, its directory exists.
+    RunQueued {
+        id: String,
+        sequence: u64,
+        custom_params: HashMap<String, String>,
+    },
+    /// Gatling was started, locally or on the agents.
+    RunStarted { id: String, sequence: u64 },
+    /// The live figures of a running testrun.
+    RunProgress { id: String, stats: LiveStats },
+    /// A testrun finished with results.
+    RunFinished {
+        id: String,
+        sequence: u64,
+        verdict: Verdict,
+    },
+    /// A testrun ended without results, e.g. because of a failed hook.
+    RunFailed { id: String, sequence: u64 },
+    /// A new version of a testsuite was uploaded.
+    SuiteUploaded { name: String },
+    /// `waterpistol.yml` was changed and reloaded.
+    ConfigChanged { config: AppConfig },
+}
+
+impl ServerEvent {
+    /// Name of the SSE event, the same as the `type` of the payload.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ServerEvent::RunQueued { .. } => "run_queued",
+            ServerEvent::RunStarted { .. } => "run_started",
+            ServerEvent::RunProgress { .. } => "run_progress",
+            ServerEvent::RunFinished { .. } => "run_finished",
+            ServerEvent::RunFailed { .. } => "run_failed",
+            ServerEvent::SuiteUploaded { .. } => "suite_uploaded",
+            ServerEvent::ConfigChanged { .. } => "config_changed",
+        }
+    }
+
+    /// Id of the testrun the event is about.
+    pub fn run_id(&self) -> Option<&str> {
+        match self {
+            ServerEvent::RunQueued { id, .. }
+            | ServerEvent::RunStarted { id, .. }
+            | ServerEvent::RunProgress { id, .. }
+            | ServerEvent::RunFinished { id, .. }
+            | ServerEvent::RunFailed { id, .. } => Some(id),
+            ServerEvent::SuiteUploaded { .. } | ServerEvent::ConfigChanged { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::ServerEvent;
+
+    #[test]
+    fn event_name_is_the_payload_type() {
+        let event = ServerEvent::RunStarted {
+            id: "abc".into(),
+            sequence: 7,
+        };
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(json["type"], event.name());
+        assert_eq!(json["id"], "abc");
+        assert_eq!(event.run_id(), Some("abc"));
+    }
+}
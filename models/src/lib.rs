@@ -1,12 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
-use report::TestrunVisibilityStatus;
+use chrono::{DateTime, Utc};
+use report::{HookResult, TestrunStatus, TestrunVisibilityStatus};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use serde_with::base64::Base64;
 
 pub mod config;
+pub mod event;
 pub mod report;
+pub mod trend;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Testrun {
@@ -16,24 +19,189 @@ pub struct Testrun {
     pub data: Option<report::TestrunData>,
 }
 
+impl Testrun {
+    /// The id of the run, older runs are identified by their directory name.
+    pub fn id(&self) -> &str {
+        match self.data {
+            Some(ref d) if !d.id.is_empty() => &d.id,
+            _ => &self.name,
+        }
+    }
+
+    fn sort_key(&self) -> (u64, Option<DateTime<Utc>>, &str) {
+        let data = self.data.as_ref();
+        (
+            data.map(|d| d.sequence).unwrap_or_default(),
+            data.and_then(|d| d.start_date()),
+            self.id(),
+        )
+    }
+}
+
 impl Eq for Testrun {}
 
 impl Ord for Testrun {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.creation_date.cmp(&other.creation_date)
+        self.sort_key().cmp(&other.sort_key())
     }
 }
 
 impl PartialEq for Testrun {
     fn eq(&self, other: &Self) -> bool {
-        self.creation_date == other.creation_date
+        self.id() == other.id()
     }
 }
 
 impl PartialOrd for Testrun {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.creation_date.partial_cmp(&other.creation_date)
+        Some(self.cmp(other))
+    }
+}
+
+/// A single testrun together with links to its files, see
+/// `GET /api/testruns/:name`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TestrunDetails {
+    pub testrun: Testrun,
+    /// The gatling HTML report, missing for failed runs or stripped reports.
+    pub report_url: Option<String>,
+    /// Console output of `mvn gatling:test`.
+    pub console_log_url: Option<String>,
+    pub simulation_log_url: Option<String>,
+    pub hooks: Vec<HookOutput>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HookOutput {
+    pub result: HookResult,
+    pub url: String,
+    pub output: String,
+}
+
+/// Filter, sort order and page of `GET /api/testruns`.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct TestrunQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub status: Option<TestrunStatus>,
+    /// Matches part of the simulation name.
+    pub simulation: Option<String>,
+    /// Comma separated `NAME:VALUE` pairs, all of which have to match.
+    pub params: Option<String>,
+    /// Comma separated tags, all of which have to be set.
+    pub tags: Option<String>,
+    /// Only runs with this visibility, without it all but hidden runs.
+    pub visibility: Option<TestrunVisibilityStatus>,
+    /// List hidden runs as well when no `visibility` is given.
+    #[serde(default)]
+    pub include_hidden: bool,
+    pub sort: Option<TestrunSort>,
+    pub order: Option<SortOrder>,
+    pub limit: Option<u32>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TestrunSort {
+    #[default]
+    Date,
+    Simulation,
+    Requests,
+    Errors,
+    ErrorRate,
+    Mean,
+    P95,
+    Max,
+    Duration,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TestrunPage {
+    pub testruns: Vec<Testrun>,
+    /// Pass as `cursor` to get the next page, `None` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct CompareQuery {
+    /// Comma separated testrun names.
+    pub runs: String,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// One figure of one request in one run of a comparison.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ComparisonRow {
+    pub request: String,
+    pub run: String,
+    pub started_at: Option<DateTime<Utc>>,
+    pub metric: String,
+    pub value: u64,
+}
+
+/// Every metric of every request in every run, requests sorted by name and
+/// runs in the given order.
+pub fn comparison_rows(testruns: &[Testrun]) -> Vec<ComparisonRow> {
+    let reports: Vec<_> = testruns
+        .iter()
+        .filter_map(|t| {
+            let data = t.data.as_ref()?;
+            Some((t.id(), data.start_date(), data.statistics.as_ref()?))
+        })
+        .collect();
+
+    let requests: BTreeSet<&str> = reports
+        .iter()
+        .flat_map(|(_, _, r)| r.request_stats.iter().map(|s| s.name.as_str()))
+        .collect();
+
+    let mut rows = vec![];
+    for request in requests {
+        for (run, started_at, report) in &reports {
+            let Some(stats) = report.request_stats.iter().find(|s| s.name == request) else {
+                continue;
+            };
+            for (metric, value) in stats.metrics() {
+                rows.push(ComparisonRow {
+                    request: request.to_string(),
+                    run: run.to_string(),
+                    started_at: *started_at,
+                    metric: metric.to_string(),
+                    value,
+                });
+            }
+        }
     }
+    rows
+}
+
+/// Testruns affected by the retention rules.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct RetentionPlan {
+    pub delete: Vec<String>,
+    pub strip_report: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -45,11 +213,92 @@ pub struct Testsuite {
 pub struct RunTestParam {
     pub description: String,
     pub custom_params: HashMap<String, String>,
+    /// Number of injector agents to distribute the run to, `0` runs it locally.
+    #[serde(default)]
+    pub agents: u32,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Default)]
+pub struct RunQuery {
+    /// Answer only after the run finished.
+    #[serde(default)]
+    pub wait: bool,
+    /// Seconds to wait at most, the run is still reported as running then.
+    pub timeout: Option<u64>,
+}
+
+/// State of a run as returned by `POST /api/run`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RunStatus {
+    pub id: String,
+    pub sequence: u64,
+    /// Where the state of the run can be polled, accepts the `RunQuery`
+    /// params as well.
+    pub status_url: String,
+    pub status: TestrunStatus,
+    /// Only set once the run finished.
+    pub verdict: Option<report::Verdict>,
+}
+
+/// Body of `POST /api/login`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Login {
+    pub name: String,
+    pub password: String,
+}
+
+/// Who is using the server, see `GET /api/me`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CurrentUser {
+    /// Name of the user or API token.
+    pub name: String,
+    pub read_only: bool,
+    /// False if no users or tokens are configured and everybody has access.
+    pub auth_enabled: bool,
+}
+
+/// Body of the requests to the webhooks, see `config::WebhookConfig`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct WebhookPayload {
+    pub event: config::WebhookEvent,
+    pub id: String,
+    pub sequence: u64,
+    pub simulation: Option<String>,
+    pub custom_params: HashMap<String, String>,
+    pub summary: Option<report::ReportSummary>,
+    pub verdict: report::Verdict,
+    /// The gatling HTML report, absolute if `public_url` is configured.
+    pub report_url: Option<String>,
+}
+
+/// Live figures of a running testrun, taken from the part of its
+/// `simulation.log` written so far. Sent by `GET /api/testruns/:name/live`.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+pub struct LiveStats {
+    /// Seconds since the start of the simulation.
+    pub elapsed: u64,
+    pub started_users: u64,
+    pub active_users: u64,
+    pub requests: u64,
+    pub errors: u64,
+    /// Requests per second over the last seconds.
+    pub rps: f64,
+    /// 95th percentile of the response times over the last seconds.
+    pub p95: Option<u64>,
+}
+
+/// Changes to a testrun, fields which are `None` are left as they are.
+#[derive(Deserialize, Serialize, Default)]
 pub struct UpdateTestrunData {
+    #[serde(default)]
     pub visibility_status: Option<TestrunVisibilityStatus>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub tags: Option<BTreeSet<String>>,
+    /// Makes the run the baseline of its preset, replacing the previous one.
+    #[serde(default)]
+    pub baseline: Option<bool>,
 }
 
 #[serde_as]
@@ -61,6 +310,21 @@ pub struct UploadTestsuite {
     pub data : Vec<u8>,
 }
 
+#[serde_as]
+#[derive(Deserialize, Serialize)]
+pub struct UploadSimulationLog {
+    pub file_name: String,
+    #[serde_as(as = "Base64")]
+    pub data: Vec<u8>,
+}
+
+/// Creates one testrun from the simulation logs of several injectors.
+#[derive(Deserialize, Serialize)]
+pub struct MergeSimulationLogs {
+    pub custom_params: HashMap<String, String>,
+    pub logs: Vec<UploadSimulationLog>,
+}
+
 #[derive(Deserialize, Serialize, PartialEq)]
 pub enum SystemStatus {
     Healthy,
@@ -72,4 +336,26 @@ pub struct SystemStatusResponse {
     pub overall: SystemStatus,
     pub maven_output: Option<String>,
     pub java_version: Option<String>,
-}
\ No newline at end of file
+}
+
+/// A run handed out by the controller to an injector agent.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AgentJob {
+    pub run_id: String,
+    pub testsuite: String,
+    pub simulation_class: String,
+    pub description: String,
+    pub params: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AgentInfo {
+    pub name: String,
+    pub last_seen: DateTime<Utc>,
+    pub busy: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AgentResultQuery {
+    pub success: bool,
+}
@@ -1,18 +1,29 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     error::Error,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, Write},
 };
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::config::{Threshold, ThresholdMetric, Tolerance};
+
+/// The figures of the requests which are compared with the baseline run.
+pub const BASELINE_METRICS: [ThresholdMetric; 4] = [
+    ThresholdMetric::P50,
+    ThresholdMetric::P95,
+    ThresholdMetric::P99,
+    ThresholdMetric::ErrorRate,
+];
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Default, Clone, Eq)]
 pub enum TestrunStatus {
     #[default]
     Unknown,
     Running,
     Done,
+    Failed,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Default, Clone, Eq)]
@@ -23,22 +34,133 @@ pub enum TestrunVisibilityStatus {
     Highlighted,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Default, Clone, Eq)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default, Clone)]
 pub struct TestrunData {
+    /// Identifies the run, independent of the name of its directory.
+    #[serde(default)]
+    pub id: String,
+    /// Increases with every run started on this server.
+    #[serde(default)]
+    pub sequence: u64,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
     pub datum: Option<DateTime<Utc>>,
     pub status: TestrunStatus,
+    /// Free text in markdown.
     #[serde(default)]
     pub notes: String,
     #[serde(default)]
+    pub tags: BTreeSet<String>,
+    #[serde(default)]
     pub visibility_status: TestrunVisibilityStatus,
     pub custom_params: HashMap<String, String>,
     pub statistics: Option<GatlingReport>,
+    #[serde(default)]
+    pub hooks: Vec<HookResult>,
+    /// New runs of the same preset are compared with this one.
+    #[serde(default)]
+    pub baseline: bool,
+    /// Comparison with the baseline at the time the run finished.
+    #[serde(default)]
+    pub regression: Option<Regression>,
+}
+
+impl TestrunData {
+    /// When the run was started, older runs only know their `datum`.
+    pub fn start_date(&self) -> Option<DateTime<Utc>> {
+        self.started_at.or(self.datum)
+    }
+
+    /// Whether both runs belong to the same preset, i.e. ran the same
+    /// simulation with the same params.
+    pub fn is_same_preset(&self, other: &TestrunData) -> bool {
+        let simulation = |d: &TestrunData| d.statistics.as_ref().map(|s| s.name.clone());
+        simulation(self).is_some()
+            && simulation(self) == simulation(other)
+            && self.custom_params == other.custom_params
+    }
+
+    /// Whether a finished run passed, i.e. it is done, stayed within the
+    /// thresholds and did not regress compared to its baseline.
+    pub fn verdict(&self, thresholds: &[Threshold]) -> Verdict {
+        let violations: Vec<_> = self
+            .statistics
+            .iter()
+            .flat_map(|s| s.request_stats.iter())
+            .flat_map(|r| r.violations(thresholds))
+            .collect();
+
+        Verdict {
+            passed: self.status == TestrunStatus::Done
+                && violations.is_empty()
+                && !self.regression.as_ref().is_some_and(|r| r.regressed()),
+            summary: self.statistics.as_ref().map(|s| s.summary()),
+            violations,
+            regression: self.regression.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Verdict {
+    pub passed: bool,
+    pub summary: Option<ReportSummary>,
+    pub violations: Vec<Violation>,
+    #[serde(default)]
+    pub regression: Option<Regression>,
+}
+
+/// Outcome of the comparison of a run with the baseline of its preset.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Regression {
+    /// Id of the baseline run.
+    pub baseline: String,
+    pub baseline_sequence: u64,
+    pub deltas: Vec<MetricDelta>,
+    /// Distribution tests of the response times of the requests, informative
+    /// only.
+    #[serde(default)]
+    pub distributions: Vec<RequestDistribution>,
+}
+
+impl Regression {
+    pub fn regressed(&self) -> bool {
+        self.deltas.iter().any(|d| d.regression)
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Eq)]
+pub enum HookKind {
+    PreRun,
+    PostRun,
+}
+
+/// Outcome of a pre- or post-run hook. The captured output lives in
+/// `output_file`, relative to the testrun directory.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Eq)]
+pub struct HookResult {
+    pub kind: HookKind,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub output_file: String,
+}
+
+impl HookResult {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Eq)]
 pub struct GatlingReport {
     pub name: String,
     pub version: String,
+    /// Start of the run in milliseconds since the epoch, as logged by gatling.
+    #[serde(default)]
+    pub start_time: u64,
+    /// Latest timestamp found in the simulation log.
+    #[serde(default)]
+    pub end_time: u64,
     pub requests_ok: u64,
     pub requests_nok: u64,
     pub request_stats: Vec<RequestStats>,
@@ -51,9 +173,15 @@ pub struct RequestStats {
     pub avg: u64,
     pub max: u64,
     pub min: u64,
+    #[serde(default)]
+    pub p50: u64,
     pub p95: u64,
+    #[serde(default)]
+    pub p99: u64,
     pub count: u64,
     pub errors: Vec<RequestErrorStats>,
+    #[serde(default)]
+    pub histogram: Histogram,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Eq)]
@@ -68,6 +196,298 @@ pub struct RequestErrorStats {
     pub count: u64,
 }
 
+/// A threshold exceeded by a request.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Violation {
+    pub request: String,
+    pub threshold: Threshold,
+    pub value: f64,
+}
+
+/// Change of a figure of a request compared to the baseline run.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct MetricDelta {
+    pub request: String,
+    pub metric: ThresholdMetric,
+    pub baseline: f64,
+    pub value: f64,
+    pub absolute: f64,
+    /// Missing if the baseline value is zero.
+    pub relative: Option<f64>,
+    /// Whether the change exceeds the tolerances.
+    pub regression: bool,
+}
+
+impl MetricDelta {
+    fn exceeds(&self, tolerance: &Tolerance) -> bool {
+        let relative = tolerance.relative.is_none_or(|max| match self.relative {
+            Some(relative) => relative > max,
+            None => self.absolute > 0.0,
+        });
+        let absolute = tolerance.absolute.is_none_or(|max| self.absolute > max);
+
+        tolerance.metric == self.metric
+            && tolerance.applies_to(&self.request)
+            && self.absolute > 0.0
+            && relative
+            && absolute
+    }
+}
+
+/// Below this p-value a difference of two response time distributions is
+/// considered significant.
+pub const SIGNIFICANCE_LEVEL: f64 = 0.01;
+
+/// Smallest effect size which counts as a change. With many requests even
+/// tiny shifts are significant, this keeps them from being reported.
+pub const MIN_EFFECT_SIZE: f64 = 0.1;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Significance {
+    Slower,
+    Faster,
+    NoChange,
+}
+
+impl Significance {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Significance::Slower => "significantly slower",
+            Significance::Faster => "significantly faster",
+            Significance::NoChange => "no change",
+        }
+    }
+}
+
+/// Result of a Mann-Whitney U test of two response time distributions.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct DistributionComparison {
+    pub u: f64,
+    pub z: f64,
+    /// Two-sided p-value of the normal approximation.
+    pub p_value: f64,
+    /// Rank-biserial correlation between -1 and 1, positive if the compared
+    /// run is slower than the reference.
+    pub effect_size: f64,
+    pub significance: Significance,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct RequestDistribution {
+    pub request: String,
+    #[serde(flatten)]
+    pub comparison: DistributionComparison,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default, Clone, Copy, Eq)]
+pub enum StatsKind {
+    #[default]
+    Request,
+    Journey,
+}
+
+/// Statistics of a request or journey, the response times are left out for
+/// journeys.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default, Clone, Eq)]
+pub struct StatsRow {
+    pub kind: StatsKind,
+    pub name: String,
+    pub count: u64,
+    pub errors: Option<u64>,
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+    pub avg: Option<u64>,
+    pub p50: Option<u64>,
+    pub p95: Option<u64>,
+    pub p99: Option<u64>,
+}
+
+/// Key figures of a run over all requests.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default, Clone)]
+pub struct ReportSummary {
+    pub requests: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub mean: u64,
+    pub p95: u64,
+    pub max: u64,
+    /// Duration of the run in milliseconds.
+    pub duration: u64,
+}
+
+/// Response time histogram in milliseconds.
+///
+/// Values are bucketed to three significant digits, so everything below one
+/// second is kept exactly. `buckets` holds `(value, count)` pairs sorted by
+/// value.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Default, Clone, Eq)]
+pub struct Histogram {
+    pub buckets: Vec<(u64, u64)>,
+}
+
+impl Histogram {
+    /// The bucket a value is counted in.
+    pub fn bucket(value: u64) -> u64 {
+        let mut scale = 1;
+        while value / scale >= 1000 {
+            scale *= 10;
+        }
+        value / scale * scale
+    }
+
+    pub fn from_sorted(values: &[u64]) -> Self {
+        let mut buckets: Vec<(u64, u64)> = vec![];
+        for v in values {
+            let b = Self::bucket(*v);
+            match buckets.last_mut() {
+                Some((value, count)) if *value == b => *count += 1,
+                _ => buckets.push((b, 1)),
+            }
+        }
+        Histogram { buckets }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().map(|(_, c)| c).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    pub fn merge(&mut self, other: &Histogram) {
+        let mut merged = Vec::with_capacity(self.buckets.len() + other.buckets.len());
+        let mut a = self.buckets.iter().peekable();
+        let mut b = other.buckets.iter().peekable();
+
+        loop {
+            match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) if x.0 == y.0 => {
+                    merged.push((x.0, x.1 + y.1));
+                    a.next();
+                    b.next();
+                }
+                (Some(x), Some(y)) if x.0 < y.0 => merged.push(*a.next().unwrap()),
+                (Some(_), Some(_)) => merged.push(*b.next().unwrap()),
+                (Some(_), None) => merged.push(*a.next().unwrap()),
+                (None, Some(_)) => merged.push(*b.next().unwrap()),
+                (None, None) => break,
+            }
+        }
+
+        self.buckets = merged;
+    }
+
+    /// Mann-Whitney U test of `other` against this histogram. Values in the
+    /// same bucket are treated as ties. `None` if either side is empty.
+    pub fn mann_whitney(&self, other: &Histogram) -> Option<DistributionComparison> {
+        let n1 = self.count() as f64;
+        let n2 = other.count() as f64;
+        if n1 == 0.0 || n2 == 0.0 {
+            return None;
+        }
+        let n = n1 + n2;
+
+        // Walk the distinct values of both sides in order, every group of ties
+        // gets the average of its ranks.
+        let mut rank_sum = 0.0;
+        let mut tie_correction = 0.0;
+        let mut seen = 0.0;
+        let mut a = self.buckets.iter().peekable();
+        let mut b = other.buckets.iter().peekable();
+        loop {
+            let (count1, count2) = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) if x.0 == y.0 => (a.next().unwrap().1, b.next().unwrap().1),
+                (Some(x), Some(y)) if x.0 < y.0 => (a.next().unwrap().1, 0),
+                (Some(_), Some(_)) => (0, b.next().unwrap().1),
+                (Some(_), None) => (a.next().unwrap().1, 0),
+                (None, Some(_)) => (0, b.next().unwrap().1),
+                (None, None) => break,
+            };
+            let t = (count1 + count2) as f64;
+            let rank = seen + (t + 1.0) / 2.0;
+            rank_sum += rank * count2 as f64;
+            tie_correction += t * t * t - t;
+            seen += t;
+        }
+
+        let u = rank_sum - n2 * (n2 + 1.0) / 2.0;
+        let mean = n1 * n2 / 2.0;
+        let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_correction / (n * (n - 1.0)));
+        let effect_size = u / mean - 1.0;
+        if variance <= 0.0 {
+            return Some(DistributionComparison {
+                u,
+                z: 0.0,
+                p_value: 1.0,
+                effect_size,
+                significance: Significance::NoChange,
+            });
+        }
+
+        let z = (u - mean) / variance.sqrt();
+        let p_value = erfc(z.abs() / std::f64::consts::SQRT_2).min(1.0);
+        let significance = if p_value >= SIGNIFICANCE_LEVEL || effect_size.abs() < MIN_EFFECT_SIZE {
+            Significance::NoChange
+        } else if effect_size > 0.0 {
+            Significance::Slower
+        } else {
+            Significance::Faster
+        };
+
+        Some(DistributionComparison {
+            u,
+            z,
+            p_value,
+            effect_size,
+            significance,
+        })
+    }
+
+    /// The value at the given percentile, using the same rank as
+    /// [`GatlingReport::from_file`] does for exact values.
+    pub fn percentile(&self, percentile: u64) -> u64 {
+        let rank = (self.count() * percentile) / 100;
+        let mut seen = 0;
+        for (value, count) in &self.buckets {
+            seen += count;
+            if seen > rank {
+                return *value;
+            }
+        }
+        0
+    }
+}
+
+/// Complementary error function, with a fractional error below 1.2e-7
+/// (Numerical Recipes, `erfcc`).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = [
+        -1.26551223,
+        1.00002368,
+        0.37409196,
+        0.09678418,
+        -0.18628806,
+        0.27886807,
+        -1.13520398,
+        1.48851587,
+        -0.82215223,
+        0.17087277,
+    ]
+    .iter()
+    .rev()
+    .fold(0.0, |acc, c| acc * t + c);
+    let r = t * (-z * z + poly).exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
 impl GatlingReport {
     pub fn from_file(stream: &mut dyn BufRead) -> Result<Self, Box<dyn Error>> {
         let rdr = BufReader::new(stream);
@@ -77,8 +497,10 @@ impl GatlingReport {
         let header = iter.next().ok_or("No header")??;
         let mut header = header.split('\t');
 
-        let name = header.nth(4).ok_or("No name")?;
-        let version = header.nth(0).ok_or("No version")?;
+        let start_time: u64 = header.nth(3).ok_or("No start")?.parse()?;
+        let name = header.next().ok_or("No name")?;
+        let version = header.next().ok_or("No version")?;
+        let mut end_time = start_time;
         let mut requests_ok = 0;
         let mut requests_nok = 0;
 
@@ -92,7 +514,7 @@ impl GatlingReport {
             let record = result?;
             let mut record = record.split('\t');
 
-            let action = record.nth(0).unwrap();
+            let action = record.next().unwrap();
 
             if action == "REQUEST" {
                 let _ = record.next().ok_or("missing field _")?;
@@ -100,6 +522,7 @@ impl GatlingReport {
                 let start: u64 = record.next().ok_or("missing field start")?.parse()?;
                 let end: u64 = record.next().ok_or("missing field end")?.parse()?;
                 let delta = end - start;
+                end_time = end_time.max(end);
                 let request_result = record.next().ok_or("missing field request_result")?;
                 if request_result == "OK" {
                     requests_ok += 1;
@@ -126,6 +549,9 @@ impl GatlingReport {
             if action == "USER" {
                 let journey = record.next().ok_or("missing field journey")?;
                 let status = record.next().ok_or("missing field status")?;
+                if let Some(Ok(timestamp)) = record.next().map(|t| t.parse::<u64>()) {
+                    end_time = end_time.max(timestamp);
+                }
                 if status == "START" {
                     user_count
                         .entry(journey.to_string())
@@ -156,20 +582,21 @@ impl GatlingReport {
                     sum += a;
                 }
 
-                let pi = (l * 95) / 100;
-                let p95 = *v.iter().nth(pi).unwrap_or(&0);
+                let percentile = |p: usize| *v.get((l * p) / 100).unwrap_or(&0);
 
                 RequestStats {
                     name: k.to_string(),
-                    min: min,
-                    max: max,
+                    min,
+                    max,
                     avg: sum / l as u64,
-                    p95: p95,
+                    p50: percentile(50),
+                    p95: percentile(95),
+                    p99: percentile(99),
                     count: l as u64,
                     errors: error_map
                         .get(k)
                         .map(|e| {
-                            e.into_iter()
+                            e.iter()
                                 .map(|(k, v)| RequestErrorStats {
                                     name: k.to_string(),
                                     count: *v,
@@ -177,6 +604,7 @@ impl GatlingReport {
                                 .collect()
                         })
                         .unwrap_or_default(),
+                    histogram: Histogram::from_sorted(v),
                 }
             })
             .collect();
@@ -184,22 +612,340 @@ impl GatlingReport {
         Ok(GatlingReport {
             name: name.to_string(),
             version: version.to_string(),
-            requests_ok: requests_ok,
-            requests_nok: requests_nok,
-            request_stats: request_stats,
+            start_time,
+            end_time,
+            requests_ok,
+            requests_nok,
+            request_stats,
             user_stats: user_count
                 .into_iter()
                 .map(|(k, v)| UserStats { name: k, count: v })
                 .collect(),
         })
     }
+
+    /// Parses the simulation logs of several injectors as one run, see
+    /// [`merge_logs`].
+    pub fn from_files(streams: &mut [&mut dyn BufRead]) -> Result<Self, Box<dyn Error>> {
+        let mut merged = vec![];
+        merge_logs(streams, &mut merged)?;
+        Self::from_file(&mut merged.as_slice())
+    }
+
+    /// Merges the reports of several injectors which ran the same simulation
+    /// at the same time.
+    ///
+    /// Runs are aligned at their start, counts and user numbers are summed up
+    /// and percentiles are taken from the merged histograms. Reports without
+    /// histograms fall back to the highest percentile of all injectors.
+    pub fn merge(reports: &[GatlingReport]) -> Option<GatlingReport> {
+        let first = reports.first()?;
+
+        let duration = reports
+            .iter()
+            .map(|r| r.end_time.saturating_sub(r.start_time))
+            .max()
+            .unwrap_or_default();
+        let start_time = reports.iter().map(|r| r.start_time).min().unwrap_or_default();
+
+        let mut requests: BTreeMap<&str, Vec<&RequestStats>> = BTreeMap::new();
+        let mut users: BTreeMap<&str, u64> = BTreeMap::new();
+
+        for report in reports {
+            for r in &report.request_stats {
+                requests.entry(&r.name).or_default().push(r);
+            }
+            for u in &report.user_stats {
+                *users.entry(&u.name).or_default() += u.count;
+            }
+        }
+
+        Some(GatlingReport {
+            name: first.name.clone(),
+            version: first.version.clone(),
+            start_time,
+            end_time: start_time + duration,
+            requests_ok: reports.iter().map(|r| r.requests_ok).sum(),
+            requests_nok: reports.iter().map(|r| r.requests_nok).sum(),
+            request_stats: requests
+                .into_iter()
+                .map(|(name, stats)| RequestStats::merge(name, &stats))
+                .collect(),
+            user_stats: users
+                .into_iter()
+                .map(|(name, count)| UserStats {
+                    name: name.to_string(),
+                    count,
+                })
+                .collect(),
+        })
+    }
+
+    /// Duration of the run in milliseconds.
+    pub fn duration(&self) -> u64 {
+        self.end_time.saturating_sub(self.start_time)
+    }
+
+    pub fn summary(&self) -> ReportSummary {
+        let requests = self.requests_ok + self.requests_nok;
+        let all = self.request_stats.iter().collect::<Vec<_>>();
+        let overall = RequestStats::merge("", &all);
+
+        ReportSummary {
+            requests,
+            errors: self.requests_nok,
+            error_rate: if requests > 0 {
+                self.requests_nok as f64 / requests as f64
+            } else {
+                0.0
+            },
+            mean: overall.avg,
+            p95: overall.p95,
+            max: overall.max,
+            duration: self.duration(),
+        }
+    }
+
+    /// Changes of the `BASELINE_METRICS` of all requests which are part of
+    /// both runs.
+    pub fn compare_to(
+        &self,
+        baseline: &GatlingReport,
+        tolerances: &[Tolerance],
+    ) -> Vec<MetricDelta> {
+        let mut deltas = vec![];
+        for stats in &self.request_stats {
+            let Some(base) = baseline.request_stats.iter().find(|r| r.name == stats.name) else {
+                continue;
+            };
+            for metric in BASELINE_METRICS {
+                let value = stats.metric(metric);
+                let baseline = base.metric(metric);
+                let mut delta = MetricDelta {
+                    request: stats.name.clone(),
+                    metric,
+                    baseline,
+                    value,
+                    absolute: value - baseline,
+                    relative: (baseline != 0.0).then(|| (value - baseline) / baseline),
+                    regression: false,
+                };
+                delta.regression = tolerances.iter().any(|t| delta.exceeds(t));
+                deltas.push(delta);
+            }
+        }
+        deltas
+    }
+
+    /// Distribution tests of the response times of all requests which are
+    /// part of both runs and have histograms.
+    pub fn compare_distributions(&self, baseline: &GatlingReport) -> Vec<RequestDistribution> {
+        self.request_stats
+            .iter()
+            .filter_map(|stats| {
+                let base = baseline
+                    .request_stats
+                    .iter()
+                    .find(|r| r.name == stats.name)?;
+                Some(RequestDistribution {
+                    request: stats.name.clone(),
+                    comparison: base.histogram.mann_whitney(&stats.histogram)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Request and journey statistics as flat rows, e.g. for CSV exports.
+    pub fn stats_rows(&self) -> Vec<StatsRow> {
+        let requests = self.request_stats.iter().map(|r| StatsRow {
+            kind: StatsKind::Request,
+            name: r.name.clone(),
+            count: r.count,
+            errors: Some(r.error_count()),
+            min: Some(r.min),
+            max: Some(r.max),
+            avg: Some(r.avg),
+            p50: Some(r.p50),
+            p95: Some(r.p95),
+            p99: Some(r.p99),
+        });
+        let journeys = self.user_stats.iter().map(|u| StatsRow {
+            kind: StatsKind::Journey,
+            name: u.name.clone(),
+            count: u.count,
+            ..Default::default()
+        });
+        requests.chain(journeys).collect()
+    }
+}
+
+impl RequestStats {
+    pub fn error_count(&self) -> u64 {
+        self.errors.iter().map(|e| e.count).sum()
+    }
+
+    pub fn metric(&self, metric: ThresholdMetric) -> f64 {
+        match metric {
+            ThresholdMetric::Avg => self.avg as f64,
+            ThresholdMetric::Max => self.max as f64,
+            ThresholdMetric::P50 => self.p50 as f64,
+            ThresholdMetric::P95 => self.p95 as f64,
+            ThresholdMetric::P99 => self.p99 as f64,
+            ThresholdMetric::Errors => self.error_count() as f64,
+            ThresholdMetric::ErrorRate if self.count > 0 => {
+                self.error_count() as f64 / self.count as f64
+            }
+            ThresholdMetric::ErrorRate => 0.0,
+        }
+    }
+
+    /// Requests per second over a run of `duration` milliseconds.
+    pub fn throughput(&self, duration: u64) -> f64 {
+        if duration == 0 {
+            return 0.0;
+        }
+        self.count as f64 * 1000.0 / duration as f64
+    }
+
+    /// The thresholds applying to this request which are exceeded.
+    pub fn violations(&self, thresholds: &[Threshold]) -> Vec<Violation> {
+        thresholds
+            .iter()
+            .filter(|t| t.applies_to(&self.name))
+            .filter_map(|t| {
+                let value = self.metric(t.metric);
+                (value > t.max).then(|| Violation {
+                    request: self.name.clone(),
+                    threshold: t.clone(),
+                    value,
+                })
+            })
+            .collect()
+    }
+
+    /// The exported figures of a request, by name.
+    pub fn metrics(&self) -> [(&'static str, u64); 8] {
+        [
+            ("count", self.count),
+            ("errors", self.error_count()),
+            ("min", self.min),
+            ("max", self.max),
+            ("avg", self.avg),
+            ("p50", self.p50),
+            ("p95", self.p95),
+            ("p99", self.p99),
+        ]
+    }
+
+    fn merge(name: &str, stats: &[&RequestStats]) -> RequestStats {
+        let count: u64 = stats.iter().map(|s| s.count).sum();
+        let weighted_sum: u64 = stats.iter().map(|s| s.avg * s.count).sum();
+
+        let mut errors: BTreeMap<&str, u64> = BTreeMap::new();
+        for e in stats.iter().flat_map(|s| &s.errors) {
+            *errors.entry(&e.name).or_default() += e.count;
+        }
+
+        let mut histogram = Histogram::default();
+        for s in stats {
+            histogram.merge(&s.histogram);
+        }
+        let complete = stats.iter().all(|s| !s.histogram.is_empty());
+
+        let percentile = |p: u64, fallback: fn(&RequestStats) -> u64| {
+            if complete {
+                histogram.percentile(p)
+            } else {
+                stats.iter().map(|s| fallback(s)).max().unwrap_or_default()
+            }
+        };
+
+        RequestStats {
+            name: name.to_string(),
+            avg: weighted_sum.checked_div(count).unwrap_or_default(),
+            max: stats.iter().map(|s| s.max).max().unwrap_or_default(),
+            min: stats.iter().map(|s| s.min).min().unwrap_or_default(),
+            p50: percentile(50, |s| s.p50),
+            p95: percentile(95, |s| s.p95),
+            p99: percentile(99, |s| s.p99),
+            count,
+            errors: errors
+                .into_iter()
+                .map(|(name, count)| RequestErrorStats {
+                    name: name.to_string(),
+                    count,
+                })
+                .collect(),
+            histogram: if complete { histogram } else { Histogram::default() },
+        }
+    }
+}
+
+/// Merges the simulation logs of several injectors into one log.
+///
+/// The header of the first log is kept and the timestamps of every other log
+/// are shifted so that all runs start at the same time.
+pub fn merge_logs(
+    streams: &mut [&mut dyn BufRead],
+    output: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let mut base_start = None;
+
+    for stream in streams.iter_mut() {
+        let mut lines = stream.lines();
+
+        let header = lines.next().ok_or("No header")??;
+        let start: i64 = header.split('\t').nth(3).ok_or("No start")?.parse()?;
+        let offset = match base_start {
+            None => {
+                base_start = Some(start);
+                writeln!(output, "{}", header)?;
+                0
+            }
+            Some(base) => base - start,
+        };
+
+        for line in lines {
+            writeln!(output, "{}", shift_timestamps(&line?, offset))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Shifts the timestamps of a simulation log record by `offset` milliseconds.
+fn shift_timestamps(line: &str, offset: i64) -> String {
+    if offset == 0 {
+        return line.to_string();
+    }
+
+    let timestamp_fields: &[usize] = match line.split('\t').next() {
+        Some("USER") => &[3],
+        Some("REQUEST") => &[3, 4],
+        Some("GROUP") => &[2, 3],
+        Some("ERROR") => &[2],
+        _ => &[],
+    };
+
+    line.split('\t')
+        .enumerate()
+        .map(|(i, field)| match field.parse::<i64>() {
+            Ok(t) if timestamp_fields.contains(&i) => (t + offset).to_string(),
+            _ => field.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\t")
 }
 
 #[cfg(test)]
 mod tests {
     use std::io::BufReader;
 
-    use crate::report::{GatlingReport, RequestErrorStats, RequestStats, UserStats};
+    use crate::config::{Threshold, ThresholdMetric, Tolerance};
+    use crate::report::{
+        merge_logs, GatlingReport, Histogram, RequestErrorStats, RequestStats, Significance,
+        UserStats,
+    };
 
     #[test]
     fn it_works() {
@@ -218,6 +964,8 @@ REQUEST\t\thome_page\t1680605883400\t1680605883512\tOK";
         let expected = GatlingReport {
             name: "foobar".to_string(),
             version: "3.9.2".to_string(),
+            start_time: 1680605882911,
+            end_time: 1680605883518,
             requests_ok: 2,
             requests_nok: 0,
             request_stats: vec![RequestStats {
@@ -225,9 +973,14 @@ REQUEST\t\thome_page\t1680605883400\t1680605883512\tOK";
                 avg: 101,
                 max: 112,
                 min: 90,
+                p50: 112,
                 p95: 112,
+                p99: 112,
                 count: 2,
                 errors: vec![],
+                histogram: Histogram {
+                    buckets: vec![(90, 1), (112, 1)],
+                },
             }],
             user_stats: vec![UserStats {
                 name: "Visit Homepage".into(),
@@ -255,6 +1008,8 @@ REQUEST\t\thome_page\t1680605883400\t1680605883512\tOK";
         let expected = GatlingReport {
             name: "foobar".to_string(),
             version: "3.9.2".to_string(),
+            start_time: 1680605882911,
+            end_time: 1680605883518,
             requests_ok: 1,
             requests_nok: 1,
             request_stats: vec![RequestStats {
@@ -262,12 +1017,17 @@ REQUEST\t\thome_page\t1680605883400\t1680605883512\tOK";
                 avg: 101,
                 max: 112,
                 min: 90,
+                p50: 112,
                 p95: 112,
+                p99: 112,
                 count: 2,
                 errors: vec![RequestErrorStats {
                     name: "BLAH".into(),
                     count: 1,
                 }],
+                histogram: Histogram {
+                    buckets: vec![(90, 1), (112, 1)],
+                },
             }],
             user_stats: vec![UserStats {
                 name: "Visit Homepage".into(),
@@ -277,4 +1037,216 @@ REQUEST\t\thome_page\t1680605883400\t1680605883512\tOK";
 
         assert_eq!(record, expected);
     }
+
+    #[test]
+    fn histogram_buckets_and_percentiles() {
+        assert_eq!(Histogram::bucket(999), 999);
+        assert_eq!(Histogram::bucket(1234), 1230);
+        assert_eq!(Histogram::bucket(98765), 98700);
+
+        let values: Vec<u64> = (1..=100).collect();
+        let histogram = Histogram::from_sorted(&values);
+
+        assert_eq!(histogram.count(), 100);
+        assert_eq!(histogram.percentile(50), 51);
+        assert_eq!(histogram.percentile(95), 96);
+        assert_eq!(histogram.percentile(99), 100);
+    }
+
+    #[test]
+    fn mann_whitney_detects_shifted_distributions() {
+        let values: Vec<u64> = (100..200).collect();
+        let reference = Histogram::from_sorted(&values);
+
+        let same = reference.mann_whitney(&reference).unwrap();
+        assert_eq!(same.effect_size, 0.0);
+        assert!((same.p_value - 1.0).abs() < 1e-6);
+        assert_eq!(same.significance, Significance::NoChange);
+
+        let shifted: Vec<u64> = (150..250).collect();
+        let slower = reference
+            .mann_whitney(&Histogram::from_sorted(&shifted))
+            .unwrap();
+        // The reference is slower in 1225 of the 10000 pairs, 50 are ties.
+        assert_eq!(slower.u, 8750.0);
+        assert!((slower.effect_size - 0.75).abs() < 1e-9);
+        assert!(slower.p_value < 1e-6);
+        assert_eq!(slower.significance, Significance::Slower);
+
+        let faster = Histogram::from_sorted(&shifted)
+            .mann_whitney(&reference)
+            .unwrap();
+        assert_eq!(faster.significance, Significance::Faster);
+
+        assert!(reference.mann_whitney(&Histogram::default()).is_none());
+    }
+
+    #[test]
+    fn merge_reports() {
+        let a = "\
+RUN\tSimulation\tsimulation\t1000\tfoobar\t3.9.2
+USER\tVisit Homepage\tSTART\t1010
+USER\tVisit Homepage\tSTART\t1020
+REQUEST\t\thome_page\t1100\t1110\tOK\t 
+REQUEST\t\thome_page\t1200\t1220\tOK\t ";
+        let b = "\
+RUN\tSimulation\tsimulation\t5000\tfoobar\t3.9.2
+USER\tVisit Homepage\tSTART\t5010
+REQUEST\t\thome_page\t5100\t5400\tKO\t 
+REQUEST\t\thome_page\t5500\t5530\tOK\t ";
+
+        let a = GatlingReport::from_file(&mut BufReader::new(a.as_bytes())).unwrap();
+        let b = GatlingReport::from_file(&mut BufReader::new(b.as_bytes())).unwrap();
+
+        let merged = GatlingReport::merge(&[a, b]).unwrap();
+
+        assert_eq!(merged.start_time, 1000);
+        assert_eq!(merged.end_time, 1530);
+        assert_eq!(merged.requests_ok, 3);
+        assert_eq!(merged.requests_nok, 1);
+        assert_eq!(
+            merged.user_stats,
+            vec![UserStats {
+                name: "Visit Homepage".into(),
+                count: 3
+            }]
+        );
+
+        let stats = &merged.request_stats[0];
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.min, 10);
+        assert_eq!(stats.max, 300);
+        assert_eq!(stats.avg, 90);
+        assert_eq!(stats.p50, 30);
+        assert_eq!(stats.p95, 300);
+        assert_eq!(
+            stats.errors,
+            vec![RequestErrorStats {
+                name: "KO".into(),
+                count: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn thresholds_are_checked_per_request() {
+        let log = "\
+RUN\tSimulation\tsimulation\t1000\tfoobar\t3.9.2
+USER\tVisit Homepage\tSTART\t1010
+REQUEST\t\thome_page\t1100\t1110\tOK\t 
+REQUEST\t\thome_page\t1200\t1500\tKO\t 
+REQUEST\t\tlogin\t1600\t1650\tOK\t ";
+        let report = GatlingReport::from_file(&mut BufReader::new(log.as_bytes())).unwrap();
+
+        let thresholds = vec![
+            Threshold {
+                request: None,
+                metric: ThresholdMetric::ErrorRate,
+                max: 0.1,
+            },
+            Threshold {
+                request: Some("login".into()),
+                metric: ThresholdMetric::Max,
+                max: 40.0,
+            },
+        ];
+
+        let home = report
+            .request_stats
+            .iter()
+            .find(|r| r.name == "home_page")
+            .unwrap();
+        let violations = home.violations(&thresholds);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].threshold, thresholds[0]);
+        assert_eq!(violations[0].value, 0.5);
+
+        let login = report
+            .request_stats
+            .iter()
+            .find(|r| r.name == "login")
+            .unwrap();
+        let violations = login.violations(&thresholds);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].value, 50.0);
+    }
+
+    #[test]
+    fn compare_to_baseline_applies_tolerances() {
+        let baseline = "\
+RUN\tSimulation\tsimulation\t1000\tfoobar\t3.9.2
+REQUEST\t\thome_page\t1100\t1200\tOK\t 
+REQUEST\t\tlogin\t1100\t1110\tOK\t ";
+        let current = "\
+RUN\tSimulation\tsimulation\t1000\tfoobar\t3.9.2
+REQUEST\t\thome_page\t1100\t1250\tOK\t 
+REQUEST\t\tlogin\t1100\t1115\tOK\t ";
+        let baseline = GatlingReport::from_file(&mut BufReader::new(baseline.as_bytes())).unwrap();
+        let current = GatlingReport::from_file(&mut BufReader::new(current.as_bytes())).unwrap();
+
+        let tolerances = vec![Tolerance {
+            request: None,
+            metric: ThresholdMetric::P95,
+            relative: Some(0.2),
+            absolute: Some(10.0),
+        }];
+        let deltas = current.compare_to(&baseline, &tolerances);
+        assert_eq!(deltas.len(), 8);
+
+        let p95 = |request: &str| {
+            deltas
+                .iter()
+                .find(|d| d.request == request && d.metric == ThresholdMetric::P95)
+                .unwrap()
+        };
+        // +50 ms and +50% exceed both limits, +5 ms and +50% only one of them
+        assert_eq!(p95("home_page").absolute, 50.0);
+        assert_eq!(p95("home_page").relative, Some(0.5));
+        assert!(p95("home_page").regression);
+        assert!(!p95("login").regression);
+        assert_eq!(deltas.iter().filter(|d| d.regression).count(), 1);
+    }
+
+    #[test]
+    fn merge_logs_aligns_timestamps() {
+        let a = "\
+RUN\tSimulation\tsimulation\t1000\tfoobar\t3.9.2
+USER\tVisit Homepage\tSTART\t1010
+REQUEST\t\thome_page\t1100\t1110\tOK\t ";
+        let b = "\
+RUN\tSimulation\tsimulation\t5000\tfoobar\t3.9.2
+USER\tVisit Homepage\tSTART\t5010
+REQUEST\t\thome_page\t5100\t5400\tOK\t ";
+
+        let mut merged = vec![];
+        merge_logs(
+            &mut [
+                &mut BufReader::new(a.as_bytes()),
+                &mut BufReader::new(b.as_bytes()),
+            ],
+            &mut merged,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(merged).unwrap(),
+            "\
+RUN\tSimulation\tsimulation\t1000\tfoobar\t3.9.2
+USER\tVisit Homepage\tSTART\t1010
+REQUEST\t\thome_page\t1100\t1110\tOK\t 
+USER\tVisit Homepage\tSTART\t1010
+REQUEST\t\thome_page\t1100\t1400\tOK\t 
+"
+        );
+
+        let report = GatlingReport::from_files(&mut [
+            &mut BufReader::new(a.as_bytes()),
+            &mut BufReader::new(b.as_bytes()),
+        ])
+        .unwrap();
+
+        assert_eq!(report.end_time, 1400);
+        assert_eq!(report.request_stats[0].count, 2);
+        assert_eq!(report.user_stats[0].count, 2);
+    }
 }
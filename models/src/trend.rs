@@ -0,0 +1,210 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::ThresholdMetric;
+use crate::report::TestrunVisibilityStatus;
+use crate::Testrun;
+
+/// Number of runs the rolling median is taken over by default.
+pub const DEFAULT_TREND_WINDOW: usize = 5;
+
+/// The figures shown in a trend.
+pub const TREND_METRICS: [ThresholdMetric; 4] = [
+    ThresholdMetric::P50,
+    ThresholdMetric::P95,
+    ThresholdMetric::P99,
+    ThresholdMetric::ErrorRate,
+];
+
+/// Filter of `GET /api/trends/:simulation`.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct TrendQuery {
+    /// Comma separated `NAME:VALUE` pairs, all of which have to match.
+    pub params: Option<String>,
+    /// Number of runs of the rolling median.
+    pub window: Option<usize>,
+}
+
+/// A run of a trend, in the order of the trend.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrendRun {
+    pub id: String,
+    pub name: String,
+    pub sequence: u64,
+    pub started_at: Option<DateTime<Utc>>,
+    pub custom_params: HashMap<String, String>,
+    pub highlighted: bool,
+    pub baseline: bool,
+    /// Free text in markdown.
+    pub notes: String,
+}
+
+impl TrendRun {
+    /// Highlighted runs and runs with notes are annotated in the charts.
+    pub fn annotation(&self) -> Option<String> {
+        let note = self.notes.lines().map(str::trim).find(|l| !l.is_empty());
+        match note {
+            Some(note) => Some(note.to_string()),
+            None if self.highlighted => Some("highlighted".into()),
+            None => None,
+        }
+    }
+}
+
+/// One figure of a request over all runs of a trend.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrendSeries {
+    pub metric: ThresholdMetric,
+    /// One value per run, missing if the request is not part of the run.
+    pub values: Vec<Option<f64>>,
+    /// Median of the last values up to the run.
+    pub median: Vec<Option<f64>>,
+    /// First and third quartile of the same values, the band around the
+    /// median.
+    pub lower: Vec<Option<f64>>,
+    pub upper: Vec<Option<f64>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RequestTrend {
+    pub request: String,
+    pub series: Vec<TrendSeries>,
+}
+
+/// Figures of the requests of a simulation across its runs, oldest first.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Trend {
+    pub simulation: String,
+    pub window: usize,
+    pub runs: Vec<TrendRun>,
+    pub requests: Vec<RequestTrend>,
+    /// The distinct params of all runs of the simulation regardless of the
+    /// `params` filter, to choose a preset from.
+    pub presets: Vec<BTreeMap<String, String>>,
+}
+
+/// The value at `q` of sorted `values`, interpolating linearly.
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let pos = (sorted.len() - 1) as f64 * q;
+    let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+}
+
+impl TrendSeries {
+    fn new(metric: ThresholdMetric, values: Vec<Option<f64>>, window: usize) -> Self {
+        let mut median = vec![];
+        let mut lower = vec![];
+        let mut upper = vec![];
+        for i in 0..values.len() {
+            let mut last: Vec<f64> = values[..=i]
+                .iter()
+                .rev()
+                .flatten()
+                .take(window)
+                .copied()
+                .collect();
+            last.sort_by(f64::total_cmp);
+            if values[i].is_none() || last.is_empty() {
+                median.push(None);
+                lower.push(None);
+                upper.push(None);
+                continue;
+            }
+            median.push(Some(quantile(&last, 0.5)));
+            lower.push(Some(quantile(&last, 0.25)));
+            upper.push(Some(quantile(&last, 0.75)));
+        }
+
+        TrendSeries {
+            metric,
+            values,
+            median,
+            lower,
+            upper,
+        }
+    }
+}
+
+/// The trend of the given runs of `simulation`, which have to be sorted
+/// oldest first. Runs without statistics are left out.
+pub fn trend(simulation: &str, testruns: &[Testrun], window: usize) -> Trend {
+    let window = window.max(1);
+    let testruns: Vec<_> = testruns
+        .iter()
+        .filter_map(|t| {
+            let data = t.data.as_ref()?;
+            Some((t, data, data.statistics.as_ref()?))
+        })
+        .collect();
+
+    let requests: BTreeSet<&str> = testruns
+        .iter()
+        .flat_map(|(_, _, r)| r.request_stats.iter().map(|s| s.name.as_str()))
+        .collect();
+
+    let requests = requests
+        .into_iter()
+        .map(|request| RequestTrend {
+            request: request.to_string(),
+            series: TREND_METRICS
+                .into_iter()
+                .map(|metric| {
+                    let values = testruns
+                        .iter()
+                        .map(|(_, _, report)| {
+                            report
+                                .request_stats
+                                .iter()
+                                .find(|s| s.name == request)
+                                .map(|s| s.metric(metric))
+                        })
+                        .collect();
+                    TrendSeries::new(metric, values, window)
+                })
+                .collect(),
+        })
+        .collect();
+
+    let runs = testruns
+        .iter()
+        .map(|(t, data, _)| TrendRun {
+            id: t.id().to_string(),
+            name: t.name.clone(),
+            sequence: data.sequence,
+            started_at: data.start_date(),
+            custom_params: data.custom_params.clone(),
+            highlighted: data.visibility_status == TestrunVisibilityStatus::Highlighted,
+            baseline: data.baseline,
+            notes: data.notes.clone(),
+        })
+        .collect();
+
+    Trend {
+        simulation: simulation.to_string(),
+        window,
+        runs,
+        requests,
+        presets: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::ThresholdMetric;
+    use crate::trend::TrendSeries;
+
+    #[test]
+    fn rolling_median_skips_missing_values() {
+        let values = vec![Some(10.0), Some(30.0), None, Some(20.0), Some(100.0)];
+        let series = TrendSeries::new(ThresholdMetric::P95, values, 3);
+
+        assert_eq!(
+            series.median,
+            vec![Some(10.0), Some(20.0), None, Some(20.0), Some(30.0)]
+        );
+        assert_eq!(series.lower[4], Some(25.0));
+        assert_eq!(series.upper[4], Some(65.0));
+    }
+}
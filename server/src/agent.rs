@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use models::AgentJob;
+use reqwest::Client;
+use tokio::fs::{create_dir_all, read, remove_dir_all};
+use tracing::{info, warn};
+
+use crate::archive::unpack_stripped;
+use crate::auth::api_client;
+use crate::gatling::{find_result_dir, gatling_command};
+
+/// Pause after a failed poll before the controller is contacted again.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+/// Upper bound for a poll request, the controller holds polls open for a while.
+const POLL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Runs the binary as injector agent: jobs are polled from the controller,
+/// executed locally and the resulting simulation log is sent back.
+pub async fn run_agent(
+    controller: &str,
+    token: Option<&str>,
+    name: &str,
+    data_dir: &Path,
+) -> Result<()> {
+    let controller = controller.trim_end_matches('/');
+    let client = api_client(token)?;
+
+    info!(controller, name, "Running as agent");
+
+    loop {
+        let job = client
+            .post(format!("{}/api/agents/{}/poll", controller, name))
+            .timeout(POLL_TIMEOUT)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+
+        let job: Option<AgentJob> = match job {
+            Ok(resp) => match resp.json().await {
+                Ok(job) => job,
+                Err(err) => {
+                    warn!(?err, "Cannot read job from controller");
+                    tokio::time::sleep(RETRY_DELAY).await;
+                    continue;
+                }
+            },
+            Err(err) => {
+                warn!(?err, "Cannot poll controller");
+                tokio::time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+        };
+
+        let Some(job) = job else {
+            continue;
+        };
+
+        info!(run_id = job.run_id, "Received job");
+
+        let result_url = format!("{}/api/agents/{}/jobs/{}", controller, name, job.run_id);
+
+        let request = match execute_job(&client, controller, data_dir, &job).await {
+            Ok(log) => client
+                .post(result_url)
+                .query(&[("success", "true")])
+                .body(log),
+            Err(err) => {
+                warn!(?err, "Job failed");
+                client
+                    .post(result_url)
+                    .query(&[("success", "false")])
+                    .body(err.to_string())
+            }
+        };
+
+        if let Err(err) = request.send().await.and_then(|r| r.error_for_status()) {
+            warn!(?err, "Cannot deliver result to controller");
+        }
+    }
+}
+
+/// Fetches the testsuite, runs gatling and returns the simulation log.
+async fn execute_job(
+    client: &Client,
+    controller: &str,
+    data_dir: &Path,
+    job: &AgentJob,
+) -> Result<Vec<u8>> {
+    let testsuite_dir = data_dir.join(&job.testsuite);
+    let results_dir: PathBuf = data_dir.join("results").join(&job.run_id);
+
+    let archive = client
+        .get(format!(
+            "{}/api/testsuites/{}/archive",
+            controller, job.testsuite
+        ))
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    // Files removed from the testsuite must not be compiled and run anymore.
+    let _ = remove_dir_all(&testsuite_dir).await;
+    create_dir_all(&testsuite_dir).await?;
+    unpack_stripped(archive.as_ref(), &testsuite_dir)?;
+    create_dir_all(&results_dir).await?;
+
+    let status = gatling_command(
+        &testsuite_dir,
+        &job.simulation_class,
+        &job.description,
+        &job.params,
+        &results_dir,
+    )
+    .status()
+    .await?;
+
+    info!(?status, "Output");
+
+    let log = match find_result_dir(&results_dir).await {
+        Some(dir) => read(dir.join("simulation.log")).await.map_err(Into::into),
+        None => Err(eyre!("Simulation did not produce any results ({})", status)),
+    };
+
+    let _ = remove_dir_all(&results_dir).await;
+
+    log
+}
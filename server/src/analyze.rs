@@ -0,0 +1,199 @@
+use std::fs::{read_to_string, write, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use chrono::DateTime;
+use clap::Args;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use models::config::AppConfig;
+use models::report::{
+    GatlingReport, MetricDelta, ReportSummary, RequestDistribution, StatsRow, TestrunData,
+    TestrunStatus, Violation,
+};
+use models::Testrun;
+use serde::Serialize;
+
+use crate::cli::{opt, output, print_deltas, print_distributions, print_table};
+use crate::junit::junit_report;
+use crate::stats::to_csv;
+
+#[derive(Args, Debug)]
+pub struct AnalyzeOpt {
+    /// the simulation.log to analyze
+    log: PathBuf,
+
+    /// compare with this testrun-data.json, using the configured tolerances
+    #[clap(long = "baseline")]
+    baseline: Option<PathBuf>,
+
+    /// write the run as testrun-data.json, which can be used as baseline
+    #[clap(long = "json-out")]
+    json_out: Option<PathBuf>,
+
+    /// write the request and journey statistics as CSV
+    #[clap(long = "csv-out")]
+    csv_out: Option<PathBuf>,
+
+    /// write a JUnit XML report
+    #[clap(long = "junit-out")]
+    junit_out: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct Analysis {
+    simulation: String,
+    summary: ReportSummary,
+    stats: Vec<StatsRow>,
+    violations: Vec<Violation>,
+    deltas: Vec<MetricDelta>,
+    distributions: Vec<RequestDistribution>,
+    passed: bool,
+}
+
+fn print_analysis(report: &GatlingReport, analysis: &Analysis) {
+    let s = &analysis.summary;
+    println!("{} (gatling {})", analysis.simulation, report.version);
+    println!(
+        "{} requests, {:.2}% failed, mean {} ms, p95 {} ms, max {} ms, {} s",
+        s.requests,
+        s.error_rate * 100.0,
+        s.mean,
+        s.p95,
+        s.max,
+        s.duration / 1000
+    );
+
+    println!();
+    let rows: Vec<Vec<String>> = analysis
+        .stats
+        .iter()
+        .map(|r| {
+            vec![
+                format!("{:?}", r.kind),
+                r.name.clone(),
+                r.count.to_string(),
+                opt(r.errors),
+                opt(r.min),
+                opt(r.max),
+                opt(r.avg),
+                opt(r.p50),
+                opt(r.p95),
+                opt(r.p99),
+            ]
+        })
+        .collect();
+    print_table(
+        &[
+            "Kind", "Name", "Count", "Errors", "Min", "Max", "Avg", "P50", "P95", "P99",
+        ],
+        &rows,
+    );
+
+    let errors: Vec<Vec<String>> = report
+        .request_stats
+        .iter()
+        .flat_map(|r| {
+            r.errors
+                .iter()
+                .map(|e| vec![r.name.clone(), e.count.to_string(), e.name.clone()])
+        })
+        .collect();
+    if !errors.is_empty() {
+        println!();
+        print_table(&["Request", "Count", "Error"], &errors);
+    }
+
+    if !analysis.deltas.is_empty() {
+        println!();
+        print_deltas(&analysis.deltas);
+    }
+    if !analysis.distributions.is_empty() {
+        println!();
+        print_distributions(&analysis.distributions);
+    }
+
+    if !analysis.violations.is_empty() {
+        println!();
+    }
+    for v in &analysis.violations {
+        println!(
+            "{}: {} is {} but must not exceed {}",
+            v.request,
+            v.threshold.metric.name(),
+            v.value,
+            v.threshold.max
+        );
+    }
+
+    println!();
+    println!("{}", if analysis.passed { "PASSED" } else { "FAILED" });
+}
+
+/// Analyzes a simulation log without a server, checking it against the
+/// thresholds of `config` and optionally a baseline run. Returns false if
+/// the run did not pass.
+pub fn analyze(opt: AnalyzeOpt, json: bool, config: &AppConfig) -> Result<bool> {
+    let f = File::open(&opt.log).map_err(|e| eyre!("Cannot open {}: {}", opt.log.display(), e))?;
+    let report = GatlingReport::from_file(&mut BufReader::new(f))
+        .map_err(|e| eyre!("Cannot read {}: {}", opt.log.display(), e))?;
+
+    let (deltas, distributions) = match opt.baseline {
+        Some(ref baseline) => {
+            let baseline: TestrunData = serde_json::from_str(&read_to_string(baseline)?)?;
+            let baseline = baseline
+                .statistics
+                .ok_or_else(|| eyre!("The baseline has no statistics"))?;
+            (
+                report.compare_to(&baseline, &config.tolerances),
+                report.compare_distributions(&baseline),
+            )
+        }
+        None => (vec![], vec![]),
+    };
+
+    let data = TestrunData {
+        started_at: DateTime::from_timestamp_millis(report.start_time as i64),
+        finished_at: DateTime::from_timestamp_millis(report.end_time as i64),
+        datum: DateTime::from_timestamp_millis(report.start_time as i64),
+        status: TestrunStatus::Done,
+        statistics: Some(report.clone()),
+        ..Default::default()
+    };
+    let verdict = data.verdict(&config.thresholds);
+
+    let analysis = Analysis {
+        simulation: report.name.clone(),
+        summary: verdict.summary.unwrap_or_default(),
+        stats: report.stats_rows(),
+        passed: verdict.passed && !deltas.iter().any(|d| d.regression),
+        violations: verdict.violations,
+        deltas,
+        distributions,
+    };
+
+    if let Some(ref path) = opt.json_out {
+        write(path, serde_json::to_string(&data)?)?;
+    }
+    if let Some(ref path) = opt.csv_out {
+        write(path, to_csv(report.stats_rows())?)?;
+    }
+    if let Some(ref path) = opt.junit_out {
+        let name = opt
+            .log
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| report.name.clone());
+        let testrun = Testrun {
+            creation_date: String::new(),
+            name,
+            progress: None,
+            data: Some(data),
+        };
+        write(path, junit_report(&testrun, &config.thresholds))?;
+    }
+
+    output(json, &analysis, |a| print_analysis(&report, a))?;
+    Ok(analysis.passed)
+}
@@ -0,0 +1,69 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tar::{Archive, Builder, EntryType};
+
+/// Directories of a testsuite which are not shipped to injector agents.
+const EXCLUDED_DIRS: [&str; 1] = ["target"];
+
+/// Unpacks a tar.gz archive into `unpack_dir`, dropping the top-level
+/// directory of every entry. Links are skipped, as they could point outside
+/// of `unpack_dir`.
+pub fn unpack_stripped(reader: impl Read, unpack_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut archive = Archive::new(GzDecoder::new(reader));
+    let mut unpacked = vec![];
+
+    for mut entry in archive.entries()?.filter_map(|e| e.ok()) {
+        let path = entry.path()?.into_owned();
+        if path.components().any(|c| c == Component::ParentDir) {
+            continue;
+        }
+        if matches!(entry.header().entry_type(), EntryType::Symlink | EntryType::Link) {
+            continue;
+        }
+        let mut components = path.components();
+        components.next();
+        let path = unpack_dir.join(components.as_path());
+        if entry.unpack(&path).is_ok() {
+            unpacked.push(path);
+        }
+    }
+
+    Ok(unpacked)
+}
+
+/// Packs a testsuite directory into a tar.gz archive with `name` as
+/// top-level directory, leaving out build output.
+pub fn pack_testsuite(testsuite_dir: &Path, name: &str) -> io::Result<Vec<u8>> {
+    pack_dir(testsuite_dir, name, &EXCLUDED_DIRS)
+}
+
+/// Packs the directory of a testrun with everything in it, i.e. data file,
+/// simulation log, HTML report, console and hook logs.
+pub fn pack_testrun(testrun_dir: &Path, name: &str) -> io::Result<Vec<u8>> {
+    pack_dir(testrun_dir, name, &[])
+}
+
+fn pack_dir(dir: &Path, name: &str, excluded: &[&str]) -> io::Result<Vec<u8>> {
+    let mut builder = Builder::new(GzEncoder::new(vec![], Compression::default()));
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        if excluded.iter().any(|d| file_name == *d) {
+            continue;
+        }
+        let archive_path = Path::new(name).join(&file_name);
+        if entry.file_type()?.is_dir() {
+            builder.append_dir_all(&archive_path, entry.path())?;
+        } else {
+            builder.append_path_with_name(entry.path(), &archive_path)?;
+        }
+    }
+
+    builder.into_inner()?.finish()
+}
@@ -35,7 +35,8 @@ where
 pub async fn static_handler(uri: Uri) -> impl IntoResponse {
     let mut path = uri.path().trim_start_matches('/').to_string();
 
-    if path == "" {
+    // Routes of the frontend, like /testruns/:name, are served the app itself.
+    if path.is_empty() || (DistAsset::get(&path).is_none() && !path.contains('.')) {
         path = "index.html".to_string();
     }
     info!("Request for {}", path);
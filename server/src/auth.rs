@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::extract::{Request, State};
+use axum::http::header::{AUTHORIZATION, COOKIE, SET_COOKIE};
+use axum::http::{HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use models::config::{AppConfig, AuthConfig};
+use models::{CurrentUser, Login};
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::error::{self, Error};
+use crate::AppState;
+
+/// Cookie with the id of the session of a logged in user.
+pub const SESSION_COOKIE: &str = "waterpistol_session";
+
+/// Hash the passwords of unknown users are checked against, so that failed
+/// logins take equally long whether the name exists or not.
+const DUMMY_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$tSJl/lrdQFqQRB5ERhwz5A$elPeEyRMAhGEFfSRiHJ2v7+LvAso+hzFVEUridxfAJA";
+
+struct Session {
+    user: String,
+    expires: Instant,
+}
+
+/// Sessions of the users logged in to the UI. They are kept in memory only,
+/// a restart logs everybody out.
+#[derive(Default)]
+pub struct Sessions {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl Sessions {
+    fn create(&self, user: &str, valid: Duration) -> String {
+        let id = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Instant::now();
+        sessions.retain(|_, s| s.expires > now);
+        sessions.insert(
+            id.clone(),
+            Session {
+                user: user.to_string(),
+                expires: now + valid,
+            },
+        );
+        id
+    }
+
+    fn user(&self, id: &str) -> Option<String> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .get(id)
+            .filter(|s| s.expires > Instant::now())
+            .map(|s| s.user.clone())
+    }
+
+    fn remove(&self, id: &str) {
+        self.sessions.lock().unwrap().remove(id);
+    }
+}
+
+/// Argon2 hash of a password for `waterpistol.yml`.
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes())
+        .map_err(|err| eyre!("Cannot create salt: {}", err))?;
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|err| eyre!("Cannot hash password: {}", err))?
+        .to_string())
+}
+
+fn verify_password(hash: &str, password: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+/// Hex SHA-256 hash of an API token for `waterpistol.yml`.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// A new random API token.
+pub fn generate_token() -> String {
+    format!("wp_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// HTTP client for the API of a server, sending `token` if given.
+pub fn api_client(token: Option<&str>) -> Result<Client> {
+    let mut headers = HeaderMap::new();
+    if let Some(token) = token {
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", token))?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+    Ok(Client::builder().default_headers(headers).build()?)
+}
+
+fn session_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(';'))
+        .filter_map(|c| c.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value)
+}
+
+/// The user of a request, by its bearer token or session cookie.
+fn identify(state: &AppState, auth: &AuthConfig, headers: &HeaderMap) -> Option<CurrentUser> {
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+    if let Some(token) = bearer {
+        let hash = hash_token(token.trim());
+        return auth
+            .tokens
+            .iter()
+            .find(|t| t.token_hash == hash)
+            .map(|t| CurrentUser {
+                name: t.name.clone(),
+                read_only: t.read_only,
+                auth_enabled: true,
+            });
+    }
+
+    let name = state.sessions.user(session_id(headers)?)?;
+    // Users removed from the configuration lose their sessions.
+    auth.users
+        .iter()
+        .find(|u| u.name == name)
+        .map(|u| CurrentUser {
+            name: u.name.clone(),
+            read_only: u.read_only,
+            auth_enabled: true,
+        })
+}
+
+/// Middleware of the API: requests need a valid token or session, read only
+/// users may only read. The user is added to the request extensions.
+pub async fn authenticate(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> error::Result<Response> {
+    let config = state.config();
+    let user = if config.auth.is_enabled() {
+        identify(&state, &config.auth, request.headers()).ok_or(Error::Unauthorized)?
+    } else {
+        CurrentUser {
+            name: "anonymous".into(),
+            read_only: false,
+            auth_enabled: false,
+        }
+    };
+    if user.read_only && !request.method().is_safe() {
+        return Err(Error::Forbidden);
+    }
+
+    request.extensions_mut().insert(user);
+    Ok(next.run(request).await)
+}
+
+/// The session cookie, only sent over HTTPS if the server is reached that way.
+fn session_cookie(config: &AppConfig, id: &str, max_age: u64) -> String {
+    let secure = config
+        .public_url
+        .as_deref()
+        .is_some_and(|url| url.starts_with("https://"));
+    format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}{}",
+        SESSION_COOKIE,
+        id,
+        max_age,
+        if secure { "; Secure" } else { "" }
+    )
+}
+
+/// Logs a user in to the UI with a session cookie.
+pub async fn post_login(
+    State(state): State<Arc<AppState>>,
+    login: Json<Login>,
+) -> error::Result<Response> {
+    let config = state.config();
+    let user = config.auth.users.iter().find(|u| u.name == login.name);
+    let hash = user.map_or(DUMMY_HASH, |u| u.password_hash.as_str());
+    let valid = verify_password(hash, &login.password);
+    let Some(user) = user.filter(|_| valid) else {
+        warn!(name = login.name, "Failed login");
+        return Err(Error::Unauthorized);
+    };
+
+    info!(name = user.name, "User logged in");
+    let valid = Duration::from_secs(config.auth.session_hours * 60 * 60);
+    let id = state.sessions.create(&user.name, valid);
+    Ok((
+        [(SET_COOKIE, session_cookie(&config, &id, valid.as_secs()))],
+        Json(CurrentUser {
+            name: user.name.clone(),
+            read_only: user.read_only,
+            auth_enabled: true,
+        }),
+    )
+        .into_response())
+}
+
+/// Ends the session of the request.
+pub async fn post_logout(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    if let Some(id) = session_id(&headers) {
+        state.sessions.remove(id);
+    }
+    [(SET_COOKIE, session_cookie(&state.config(), "", 0))].into_response()
+}
+
+/// The user of the request.
+pub async fn get_me(Extension(user): Extension<CurrentUser>) -> Json<CurrentUser> {
+    Json(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::http::header::{AUTHORIZATION, COOKIE, SET_COOKIE};
+    use axum::http::{HeaderMap, HeaderValue, StatusCode};
+    use axum::middleware;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use models::config::{AppConfig, AuthConfig, TokenConfig, UserConfig};
+    use models::Login;
+    use reqwest::Client;
+    use tempfile::{tempdir, TempDir};
+    use tokio::net::TcpListener;
+
+    use crate::auth::{
+        authenticate, hash_password, hash_token, post_login, session_id, verify_password,
+    };
+    use crate::AppState;
+
+    /// Serves `/api/runs`, which can be read and written, behind the
+    /// middleware. Returns its URL.
+    async fn serve(config: AppConfig) -> (String, TempDir) {
+        let tmp = tempdir().unwrap();
+        let state: Arc<AppState> = AppState::for_tests(tmp.path(), config);
+        let app = Router::new()
+            .route(
+                "/api/runs",
+                get(|| async { "read" }).post(|| async { "written" }),
+            )
+            .route_layer(middleware::from_fn_with_state(state.clone(), authenticate))
+            .route("/api/login", post(post_login))
+            .with_state(state);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/api", addr), tmp)
+    }
+
+    fn config() -> AppConfig {
+        AppConfig {
+            public_url: Some("https://waterpistol.example.com".into()),
+            auth: AuthConfig {
+                users: vec![UserConfig {
+                    name: "alice".into(),
+                    password_hash: hash_password("secret").unwrap(),
+                    read_only: false,
+                }],
+                tokens: vec![
+                    TokenConfig {
+                        name: "ci".into(),
+                        token_hash: hash_token("wp_ci"),
+                        read_only: false,
+                    },
+                    TokenConfig {
+                        name: "dashboard".into(),
+                        token_hash: hash_token("wp_dashboard"),
+                        read_only: true,
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn api_needs_a_token_and_read_only_tokens_cannot_write() {
+        let (url, _tmp) = serve(config()).await;
+        let client = Client::new();
+        let status = |request: reqwest::RequestBuilder| async move {
+            request.send().await.unwrap().status()
+        };
+        let runs = format!("{}/runs", url);
+
+        assert_eq!(status(client.get(&runs)).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status(client.get(&runs).bearer_auth("wp_unknown")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(client.get(&runs).bearer_auth("wp_ci")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(client.post(&runs).bearer_auth("wp_ci")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(client.get(&runs).bearer_auth("wp_dashboard")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(client.post(&runs).bearer_auth("wp_dashboard")).await,
+            StatusCode::FORBIDDEN
+        );
+
+        let (url, _tmp) = serve(AppConfig::default()).await;
+        let runs = format!("{}/runs", url);
+        assert_eq!(status(client.post(&runs)).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn login_sets_a_secure_session_cookie() {
+        let (url, _tmp) = serve(config()).await;
+        let client = Client::new();
+        let login = |name: &str, password: &str| {
+            client.post(format!("{}/login", url)).json(&Login {
+                name: name.into(),
+                password: password.into(),
+            })
+        };
+
+        let resp = login("alice", "wrong").send().await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = login("bob", "secret").send().await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = login("alice", "secret").send().await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let cookie = resp.headers()[SET_COOKIE].to_str().unwrap().to_string();
+        assert!(cookie.ends_with("; Secure"), "{}", cookie);
+        let session = cookie.split(';').next().unwrap().to_string();
+
+        let resp = client
+            .post(format!("{}/runs", url))
+            .header(COOKIE, &session)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.text().await.unwrap(), "written");
+
+        let resp = client
+            .get(format!("{}/runs", url))
+            .header(AUTHORIZATION, "Bearer wp_unknown")
+            .header(COOKIE, &session)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn password_hash_verifies_only_the_password() {
+        let hash = hash_password("secret").unwrap();
+
+        assert!(hash.starts_with("$argon2"));
+        assert!(verify_password(&hash, "secret"));
+        assert!(!verify_password(&hash, "Secret"));
+        assert!(!verify_password("not a hash", "secret"));
+    }
+
+    #[test]
+    fn session_is_read_from_cookies() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            COOKIE,
+            HeaderValue::from_static("theme=dark; waterpistol_session=abc"),
+        );
+
+        assert_eq!(session_id(&headers), Some("abc"));
+    }
+}
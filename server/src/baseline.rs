@@ -0,0 +1,154 @@
+use models::report::{Regression, TestrunData};
+use models::Testrun;
+use tracing::{info, warn};
+
+use crate::controller::save_testrun;
+use crate::error;
+use crate::AppState;
+
+/// The baselines of the preset of `data`, other than `data` itself.
+fn baselines(state: &AppState, data: &TestrunData) -> error::Result<Vec<Testrun>> {
+    let Some(ref report) = data.statistics else {
+        return Ok(vec![]);
+    };
+    Ok(state
+        .index
+        .baselines(&report.name)?
+        .into_iter()
+        .filter(|t| {
+            t.data
+                .as_ref()
+                .is_some_and(|d| d.id != data.id && d.is_same_preset(data))
+        })
+        .collect())
+}
+
+/// Compares a finished run with the baseline of its preset, if there is one.
+pub fn compare_with_baseline(state: &AppState, data: &TestrunData) -> Option<Regression> {
+    let report = data.statistics.as_ref()?;
+    let baseline = match baselines(state, data) {
+        Ok(baselines) => baselines.into_iter().next()?,
+        Err(err) => {
+            warn!(?err, "Cannot look up baseline");
+            return None;
+        }
+    };
+    let baseline_data = baseline.data.as_ref()?;
+    let baseline_report = baseline_data.statistics.as_ref()?;
+
+    let regression = Regression {
+        baseline: baseline.id().to_string(),
+        baseline_sequence: baseline_data.sequence,
+        deltas: report.compare_to(baseline_report, &state.config().tolerances),
+        distributions: report.compare_distributions(baseline_report),
+    };
+    if regression.regressed() {
+        info!(baseline = regression.baseline, "Testrun regressed");
+    }
+    Some(regression)
+}
+
+/// Removes the baseline mark of the other runs of the preset of `data`.
+pub async fn replace_baseline(state: &AppState, data: &TestrunData) -> error::Result<()> {
+    for testrun in baselines(state, data)? {
+        let Some(mut other) = testrun.data else {
+            continue;
+        };
+        other.baseline = false;
+        save_testrun(state, &state.result_dir.join(&testrun.name), &other).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use models::config::{AppConfig, ThresholdMetric, Tolerance};
+    use models::report::TestrunData;
+    use tempfile::tempdir;
+    use tokio::fs::create_dir_all;
+
+    use crate::baseline::{compare_with_baseline, replace_baseline};
+    use crate::controller::{save_testrun, DATA_FILE};
+    use crate::test_util::finished;
+    use crate::AppState;
+
+    /// A finished run of `Sim` with `users` as param and one request.
+    fn run(id: &str, sequence: u64, users: &str, took: u64, baseline: bool) -> TestrunData {
+        TestrunData {
+            sequence,
+            custom_params: HashMap::from([("users".to_string(), users.to_string())]),
+            baseline,
+            ..finished(id, "Sim", &[("home", took, true)])
+        }
+    }
+
+    async fn save(state: &AppState, data: &TestrunData) {
+        let dir = state.result_dir.join(&data.id);
+        create_dir_all(&dir).await.unwrap();
+        save_testrun(state, &dir, data).await.unwrap();
+    }
+
+    fn is_baseline(state: &AppState, id: &str) -> bool {
+        let testruns = state.index.all().unwrap();
+        let testrun = testruns.iter().find(|t| t.name == id).unwrap();
+        testrun.data.as_ref().unwrap().baseline
+    }
+
+    #[tokio::test]
+    async fn runs_are_compared_with_the_baseline_of_their_preset() {
+        let tmp = tempdir().unwrap();
+        let config = AppConfig {
+            tolerances: vec![Tolerance {
+                request: None,
+                metric: ThresholdMetric::P95,
+                relative: Some(0.1),
+                absolute: None,
+            }],
+            ..Default::default()
+        };
+        let state = AppState::for_tests(tmp.path(), config);
+        save(&state, &run("base", 1, "10", 100, true)).await;
+        save(&state, &run("other", 2, "20", 500, true)).await;
+        save(&state, &run("plain", 3, "10", 50, false)).await;
+
+        let regression = compare_with_baseline(&state, &run("new", 4, "10", 300, false)).unwrap();
+        assert_eq!(regression.baseline, "base");
+        assert_eq!(regression.baseline_sequence, 1);
+        let p95 = regression
+            .deltas
+            .iter()
+            .find(|d| d.metric == ThresholdMetric::P95)
+            .unwrap();
+        assert_eq!((p95.baseline, p95.value), (100.0, 300.0));
+        assert!(regression.regressed());
+
+        let regression = compare_with_baseline(&state, &run("new", 4, "20", 500, false)).unwrap();
+        assert_eq!(regression.baseline, "other");
+        assert!(!regression.regressed());
+
+        assert!(compare_with_baseline(&state, &run("new", 4, "30", 100, false)).is_none());
+        assert!(compare_with_baseline(&state, &run("base", 1, "10", 100, true)).is_none());
+    }
+
+    #[tokio::test]
+    async fn only_the_baseline_of_the_same_preset_is_replaced() {
+        let tmp = tempdir().unwrap();
+        let state = AppState::for_tests(tmp.path(), AppConfig::default());
+        save(&state, &run("base", 1, "10", 100, true)).await;
+        save(&state, &run("other", 2, "20", 100, true)).await;
+        let new = run("new", 3, "10", 100, false);
+        save(&state, &new).await;
+
+        replace_baseline(&state, &new).await.unwrap();
+
+        assert!(!is_baseline(&state, "base"));
+        assert!(is_baseline(&state, "other"));
+        let on_disk: TestrunData = serde_json::from_slice(
+            &std::fs::read(state.result_dir.join("base").join(DATA_FILE)).unwrap(),
+        )
+        .unwrap();
+        assert!(!on_disk.baseline);
+    }
+}
@@ -0,0 +1,574 @@
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use models::report::{MetricDelta, RequestDistribution, TestrunStatus, Verdict};
+use models::{
+    ComparisonRow, RunStatus, RunTestParam, Testrun, TestrunDetails, TestrunPage, TestrunQuery,
+    UploadTestsuite,
+};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::fs::{read, write};
+
+use crate::auth::api_client;
+
+/// Commands which act as client of a remote waterpistol server.
+#[derive(Subcommand, Debug)]
+pub enum ClientCommand {
+    /// upload a testsuite archive (tar.gz)
+    Upload { file: PathBuf },
+
+    /// start a testrun
+    Run {
+        /// set the description of the run
+        #[clap(short = 'd', long = "description", default_value = "")]
+        description: String,
+
+        /// set a simulation param, can be given several times
+        #[clap(short = 'P', long = "param", value_parser = parse_param)]
+        params: Vec<(String, String)>,
+
+        /// set the number of injector agents, 0 runs the simulation on the server
+        #[clap(long = "agents", default_value = "0")]
+        agents: u32,
+
+        /// wait for the run to finish, exits with 1 if it did not pass
+        #[clap(long = "wait")]
+        wait: bool,
+
+        /// set the seconds to wait at most
+        #[clap(long = "timeout")]
+        timeout: Option<u64>,
+    },
+
+    /// wait for a testrun to finish, exits with 1 if it did not pass
+    Wait {
+        id: String,
+
+        /// set the seconds to wait at most
+        #[clap(long = "timeout")]
+        timeout: Option<u64>,
+    },
+
+    /// list the latest testruns
+    List {
+        /// only runs whose simulation name contains this
+        #[clap(long = "simulation")]
+        simulation: Option<String>,
+
+        /// only runs with this status (Running, Done, Failed)
+        #[clap(long = "status", value_parser = parse_variant::<TestrunStatus>)]
+        status: Option<TestrunStatus>,
+
+        /// only runs with all of these comma separated tags
+        #[clap(long = "tags")]
+        tags: Option<String>,
+
+        /// list hidden runs as well
+        #[clap(long = "all")]
+        all: bool,
+
+        /// set the number of runs listed
+        #[clap(long = "limit", default_value = "20")]
+        limit: u32,
+    },
+
+    /// show a testrun with its statistics
+    Show { name: String },
+
+    /// compare the statistics of testruns
+    Compare {
+        #[clap(required = true)]
+        runs: Vec<String>,
+    },
+
+    /// download a testrun as tar.gz archive
+    Export {
+        name: String,
+
+        /// set the file to write, `<name>.tar.gz` by default
+        #[clap(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+    },
+}
+
+fn parse_param(s: &str) -> std::result::Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .ok_or_else(|| format!("{} is not of the form NAME=VALUE", s))
+}
+
+/// Parses an enum variant by its serialized name.
+fn parse_variant<T: DeserializeOwned>(s: &str) -> std::result::Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(s.to_string())).map_err(|e| e.to_string())
+}
+
+struct Remote {
+    client: Client,
+    url: String,
+}
+
+impl Remote {
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.client.get(format!("{}{}", self.url, path))
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        self.client.post(format!("{}{}", self.url, path))
+    }
+}
+
+/// Sends the request, turning error statuses into errors. The server answers
+/// with 204 for unknown testruns.
+async fn send(request: RequestBuilder) -> Result<Response> {
+    let resp = request.send().await?;
+    let status = resp.status();
+    if status == StatusCode::NO_CONTENT {
+        return Err(eyre!("Not found"));
+    }
+    if !status.is_success() {
+        return Err(eyre!(
+            "{}: {}",
+            status,
+            resp.text().await.unwrap_or_default()
+        ));
+    }
+    Ok(resp)
+}
+
+async fn fetch<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+    Ok(send(request).await?.json().await?)
+}
+
+/// Prints `value` as JSON or in human readable form.
+pub fn output<T: Serialize>(json: bool, value: &T, human: impl FnOnce(&T)) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(value)?);
+    } else {
+        human(value);
+    }
+    Ok(())
+}
+
+pub fn print_table(header: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: &mut dyn Iterator<Item = &str>| {
+        let line = cells
+            .zip(&widths)
+            .map(|(c, w)| format!("{:<w$}", c, w = *w))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    };
+
+    line(&mut header.iter().copied());
+    for row in rows {
+        line(&mut row.iter().map(String::as_str));
+    }
+}
+
+pub fn opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or("-".into())
+}
+
+fn print_testruns(testruns: &[Testrun]) {
+    let rows: Vec<Vec<String>> = testruns
+        .iter()
+        .map(|t| {
+            let data = t.data.clone().unwrap_or_default();
+            let summary = data.statistics.as_ref().map(|s| s.summary());
+            vec![
+                format!("#{}", data.sequence),
+                t.id().to_string(),
+                opt(data.start_date().map(|d| d.format("%Y-%m-%d %H:%M:%S"))),
+                match (data.baseline, &data.regression) {
+                    (true, _) => format!("{:?} (baseline)", data.status),
+                    (_, Some(r)) if r.regressed() => format!("{:?} (regressed)", data.status),
+                    _ => format!("{:?}", data.status),
+                },
+                opt(data.statistics.as_ref().map(|s| &s.name)),
+                opt(summary.as_ref().map(|s| s.requests)),
+                opt(summary
+                    .as_ref()
+                    .map(|s| format!("{:.2}%", s.error_rate * 100.0))),
+                opt(summary.as_ref().map(|s| s.mean)),
+                opt(summary.as_ref().map(|s| s.p95)),
+                data.tags.into_iter().collect::<Vec<_>>().join(","),
+            ]
+        })
+        .collect();
+
+    print_table(
+        &[
+            "Run",
+            "Id",
+            "Started",
+            "Status",
+            "Simulation",
+            "Requests",
+            "Failure%",
+            "Mean",
+            "P95",
+            "Tags",
+        ],
+        &rows,
+    );
+}
+
+fn print_details(details: &TestrunDetails) {
+    let testrun = &details.testrun;
+    let data = testrun.data.clone().unwrap_or_default();
+
+    println!("Run:      #{} ({})", data.sequence, testrun.id());
+    println!(
+        "Started:  {}",
+        opt(data.start_date().map(|d| d.format("%Y-%m-%d %H:%M:%S")))
+    );
+    println!(
+        "Finished: {}",
+        opt(data.finished_at.map(|d| d.format("%Y-%m-%d %H:%M:%S")))
+    );
+    println!("Status:   {:?}", data.status);
+    let mut params: Vec<_> = data.custom_params.iter().collect();
+    params.sort();
+    for (name, value) in params {
+        println!("Param:    {}={}", name, value);
+    }
+    if !data.tags.is_empty() {
+        println!(
+            "Tags:     {}",
+            data.tags.iter().cloned().collect::<Vec<_>>().join(",")
+        );
+    }
+    if !data.notes.is_empty() {
+        println!("\n{}", data.notes);
+    }
+
+    if let Some(ref report) = data.statistics {
+        println!();
+        let rows: Vec<Vec<String>> = report
+            .stats_rows()
+            .into_iter()
+            .map(|r| {
+                vec![
+                    format!("{:?}", r.kind),
+                    r.name,
+                    r.count.to_string(),
+                    opt(r.errors),
+                    opt(r.min),
+                    opt(r.max),
+                    opt(r.avg),
+                    opt(r.p50),
+                    opt(r.p95),
+                    opt(r.p99),
+                ]
+            })
+            .collect();
+        print_table(
+            &[
+                "Kind", "Name", "Count", "Errors", "Min", "Max", "Avg", "P50", "P95", "P99",
+            ],
+            &rows,
+        );
+    }
+}
+
+fn print_comparison(rows: &[ComparisonRow]) {
+    let mut runs: Vec<&str> = vec![];
+    let mut table: Vec<Vec<String>> = vec![];
+
+    for row in rows {
+        let column = match runs.iter().position(|r| *r == row.run) {
+            Some(i) => i,
+            None => {
+                runs.push(&row.run);
+                runs.len() - 1
+            }
+        };
+        let line = match table
+            .iter_mut()
+            .find(|l| l[0] == row.request && l[1] == row.metric)
+        {
+            Some(line) => line,
+            None => {
+                table.push(vec![row.request.clone(), row.metric.clone()]);
+                table.last_mut().unwrap()
+            }
+        };
+        if line.len() < column + 3 {
+            line.resize(column + 3, "-".into());
+        }
+        line[column + 2] = row.value.to_string();
+    }
+
+    let mut header = vec!["Request", "Metric"];
+    header.extend(runs);
+    print_table(&header, &table);
+}
+
+fn print_status(status: &RunStatus) {
+    println!(
+        "Run #{} ({}): {:?}",
+        status.sequence, status.id, status.status
+    );
+
+    let Some(Verdict {
+        passed,
+        ref summary,
+        ref violations,
+        ref regression,
+    }) = status.verdict
+    else {
+        println!("Status: {}", status.status_url);
+        return;
+    };
+
+    if let Some(s) = summary {
+        println!(
+            "{} requests, {:.2}% failed, mean {} ms, p95 {} ms, max {} ms",
+            s.requests,
+            s.error_rate * 100.0,
+            s.mean,
+            s.p95,
+            s.max
+        );
+    }
+    for v in violations {
+        println!(
+            "{}: {} is {} but must not exceed {}",
+            v.request,
+            v.threshold.metric.name(),
+            v.value,
+            v.threshold.max
+        );
+    }
+    if let Some(r) = regression {
+        println!(
+            "Compared with baseline #{} ({}):",
+            r.baseline_sequence, r.baseline
+        );
+        print_deltas(&r.deltas);
+        if !r.distributions.is_empty() {
+            println!();
+            print_distributions(&r.distributions);
+        }
+    }
+    println!("{}", if passed { "PASSED" } else { "FAILED" });
+}
+
+pub fn print_deltas(deltas: &[MetricDelta]) {
+    let rows: Vec<Vec<String>> = deltas
+        .iter()
+        .map(|d| {
+            vec![
+                d.request.clone(),
+                d.metric.name().to_string(),
+                d.baseline.to_string(),
+                d.value.to_string(),
+                format!("{:+}", d.absolute),
+                opt(d.relative.map(|r| format!("{:+.1}%", r * 100.0))),
+                if d.regression { "REGRESSION" } else { "" }.to_string(),
+            ]
+        })
+        .collect();
+    print_table(
+        &[
+            "Request", "Metric", "Baseline", "Value", "Delta", "Delta%", "",
+        ],
+        &rows,
+    );
+}
+
+pub fn print_distributions(distributions: &[RequestDistribution]) {
+    let rows: Vec<Vec<String>> = distributions
+        .iter()
+        .map(|d| {
+            vec![
+                d.request.clone(),
+                format!("{:+.2}", d.comparison.effect_size),
+                format!("{:.4}", d.comparison.p_value),
+                d.comparison.significance.name().to_string(),
+            ]
+        })
+        .collect();
+    print_table(&["Request", "Effect size", "p", "Distribution"], &rows);
+}
+
+/// Whether a waited for run passed, runs still going count as failed.
+fn passed(status: &RunStatus) -> bool {
+    status.verdict.as_ref().is_some_and(|v| v.passed)
+}
+
+/// Executes a client command against the server at `url`. Returns false if
+/// a waited for run did not pass.
+pub async fn run_client(
+    url: &str,
+    token: Option<&str>,
+    json: bool,
+    command: ClientCommand,
+) -> Result<bool> {
+    let remote = Remote {
+        client: api_client(token)?,
+        url: url.trim_end_matches('/').to_string(),
+    };
+
+    match command {
+        ClientCommand::Upload { file } => {
+            let upload = UploadTestsuite {
+                file_name: file
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                mime_type: "application/gzip".into(),
+                data: read(&file).await?,
+            };
+            send(remote.post("/api/testsuites/upload").json(&upload)).await?;
+            if !json {
+                println!("Uploaded {}", file.display());
+            }
+        }
+        ClientCommand::Run {
+            description,
+            params,
+            agents,
+            wait,
+            timeout,
+        } => {
+            let param = RunTestParam {
+                description,
+                custom_params: params.into_iter().collect(),
+                agents,
+            };
+            let mut request = remote.post("/api/run").json(&param);
+            if wait {
+                request = request.query(&[("wait", "true")]);
+            }
+            if let Some(timeout) = timeout {
+                request = request.query(&[("timeout", timeout)]);
+            }
+            let status: RunStatus = fetch(request).await?;
+            output(json, &status, print_status)?;
+            return Ok(!wait || passed(&status));
+        }
+        ClientCommand::Wait { id, timeout } => {
+            let mut request = remote
+                .get(&format!("/api/testruns/{}/status", id))
+                .query(&[("wait", "true")]);
+            if let Some(timeout) = timeout {
+                request = request.query(&[("timeout", timeout)]);
+            }
+            let status: RunStatus = fetch(request).await?;
+            output(json, &status, print_status)?;
+            return Ok(passed(&status));
+        }
+        ClientCommand::List {
+            simulation,
+            status,
+            tags,
+            all,
+            limit,
+        } => {
+            let query = TestrunQuery {
+                simulation,
+                status,
+                tags,
+                include_hidden: all,
+                limit: Some(limit),
+                ..Default::default()
+            };
+            let page: TestrunPage = fetch(remote.get("/api/testruns").query(&query)).await?;
+            output(json, &page.testruns, |t| print_testruns(t))?;
+        }
+        ClientCommand::Show { name } => {
+            let details: TestrunDetails =
+                fetch(remote.get(&format!("/api/testruns/{}", name))).await?;
+            output(json, &details, print_details)?;
+        }
+        ClientCommand::Compare { runs } => {
+            let rows: Vec<ComparisonRow> = fetch(
+                remote
+                    .get("/api/testruns/compare")
+                    .query(&[("runs", runs.join(","))]),
+            )
+            .await?;
+            output(json, &rows, |r| print_comparison(r))?;
+        }
+        ClientCommand::Export { name, output } => {
+            let archive = send(remote.get(&format!("/api/testruns/{}/export", name)))
+                .await?
+                .bytes()
+                .await?;
+            let file = output.unwrap_or_else(|| PathBuf::from(format!("{}.tar.gz", name)));
+            write(&file, archive).await?;
+            if !json {
+                println!("Exported {} to {}", name, file.display());
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use models::report::{TestrunStatus, Verdict};
+    use models::RunStatus;
+
+    use crate::cli::{parse_param, parse_variant, passed};
+
+    #[test]
+    fn params_are_split_at_the_first_equals_sign() {
+        assert_eq!(
+            parse_param("users=10"),
+            Ok(("users".to_string(), "10".to_string()))
+        );
+        assert_eq!(
+            parse_param("query=a=b"),
+            Ok(("query".to_string(), "a=b".to_string()))
+        );
+        assert_eq!(
+            parse_param("empty="),
+            Ok(("empty".to_string(), String::new()))
+        );
+        assert!(parse_param("users").is_err());
+    }
+
+    #[test]
+    fn variants_are_parsed_by_their_serialized_name() {
+        assert_eq!(
+            parse_variant::<TestrunStatus>("Done"),
+            Ok(TestrunStatus::Done)
+        );
+        assert!(parse_variant::<TestrunStatus>("done").is_err());
+        assert!(parse_variant::<TestrunStatus>("Finished").is_err());
+    }
+
+    #[test]
+    fn only_finished_runs_with_a_passed_verdict_pass() {
+        let status = |verdict: Option<bool>| RunStatus {
+            id: "abc".into(),
+            sequence: 1,
+            status_url: "/api/testruns/abc/status".into(),
+            status: TestrunStatus::Done,
+            verdict: verdict.map(|passed| Verdict {
+                passed,
+                summary: None,
+                violations: vec![],
+                regression: None,
+            }),
+        };
+
+        assert!(passed(&status(Some(true))));
+        assert!(!passed(&status(Some(false))));
+        assert!(!passed(&status(None)));
+    }
+}
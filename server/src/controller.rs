@@ -1,38 +1,67 @@
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::extract::{Path, Query, State};
+use axum::body::Bytes;
+use axum::http::{header, StatusCode};
 use axum::Json;
 use chrono::{DateTime, Local, Utc};
 use error::Error;
-use flate2::read::GzDecoder;
-use log::warn;
 use models::config::AppConfig;
+use models::event::ServerEvent;
 
-use axum::response::IntoResponse;
-use models::report::{GatlingReport, TestrunData, TestrunStatus, TestrunVisibilityStatus};
-use models::{RunTestParam, SystemStatusResponse, Testrun, Testsuite, UpdateTestrunData, UploadTestsuite};
-use tar::Archive;
+use axum::response::{IntoResponse, Response};
+use models::report::{GatlingReport, HookKind, HookResult, TestrunData, TestrunStatus};
+use models::{
+    AgentJob, HookOutput, MergeSimulationLogs, RunQuery, RunStatus, RunTestParam,
+    SystemStatusResponse, Testrun, TestrunDetails, TestrunPage, TestrunQuery, Testsuite,
+    UpdateTestrunData, UploadTestsuite,
+};
 
-use std::io::{BufReader, Write};
-use std::path::PathBuf;
+use std::future::Future;
+use std::io::BufReader;
+use std::path::{self, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs::{self, create_dir_all, read_dir, read_to_string, remove_dir_all, rename, File};
 use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
-use tracing::info;
+use tokio::time::{sleep, Instant};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
-use color_eyre::Result;
-
+use crate::archive::{pack_testrun, unpack_stripped};
+use crate::baseline::{compare_with_baseline, replace_baseline};
+use crate::distributed::run_distributed;
+use crate::events::run_ended;
+use crate::gatling::{find_result_dir, gatling_command, merge_injector_logs, simulation_params};
+use crate::hooks::run_hook;
+use crate::index::data_modified;
+use crate::webhooks::notify;
 use crate::{error, AppState, TESTSUITE_NAME};
 
+/// The data of a testrun next to its report.
+pub const DATA_FILE: &str = "testrun-data.json";
+/// Console output of gatling, kept next to the report.
+const CONSOLE_LOG: &str = "console.log";
+/// How long `wait=true` waits for a run at most, in seconds.
+const DEFAULT_WAIT_TIMEOUT: u64 = 60 * 60;
+/// How often a waiting request checks whether the run finished.
+const WAIT_INTERVAL: Duration = Duration::from_secs(1);
+/// Names of routes below `/api/testruns`, which cannot be testrun ids.
+const RESERVED_IDS: [&str; 3] = ["compare", "import", "merge"];
+
 async fn read_data_file(data_file: &PathBuf) -> error::Result<TestrunData> {
     let contents = fs::read(&data_file).await?;
     let contents = String::from_utf8_lossy(&contents);
     Ok(serde_json::from_str(&contents)?)
 }
 
+async fn write_data_file(data_file: &path::Path, data: &TestrunData) -> error::Result<()> {
+    let mut f = File::create(data_file).await?;
+    f.write_all(serde_json::to_string(data)?.as_bytes()).await?;
+    Ok(())
+}
+
 pub async fn get_config(State(state): State<Arc<AppState>>) -> error::Result<Json<AppConfig>> {
-    Ok(Json(state.app_config.clone()))
+    Ok(Json(state.config().as_ref().clone()))
 }
 
 pub async fn get_status(State(_state): State<Arc<AppState>>) -> error::Result<Json<SystemStatusResponse>> {
@@ -61,122 +90,130 @@ pub async fn get_status(State(_state): State<Arc<AppState>>) -> error::Result<Js
 
 }
 
-pub async fn get_testruns(State(state): State<Arc<AppState>>) -> error::Result<Json<Vec<Testrun>>> {
-    let mut res: Vec<Testrun> = vec![];
-
-    let Ok(dir) = read_dir(&state.result_dir).await else {
-        return Err(Error::NotFound);
-    };
+pub async fn get_testruns(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TestrunQuery>,
+) -> error::Result<Json<TestrunPage>> {
+    let mut res = state.index.query(&query)?;
 
-    let mut x = dir;
-    loop {
-        match x.next_entry().await {
-            Ok(Some(e)) => {
-                if e.path().is_dir() && !e.file_name().to_string_lossy().starts_with("running-"){
-                    let data_file = e.path().join("testrun-data.json");
-                    let data: TestrunData = match read_data_file(&data_file).await {
-                        Ok(df) => df,
-                        Err(err) => {
-                            warn!(
-                                "Cannot read data file {:?} because of {:?}",
-                                &data_file, err
-                            );
-                            let simulation_log_file = e.path().join("simulation.log");
-                            let data = if simulation_log_file.exists() {
-                                let f = std::fs::File::open(&simulation_log_file).unwrap();
-                                let report = GatlingReport::from_file(&mut BufReader::new(&f)).ok();
-
-                                TestrunData {
-                                    statistics: report,
-                                    ..Default::default()
-                                }
-                            } else {
-                                Default::default()
-                            };
-                            {
-                                let mut f = File::create(&data_file).await.unwrap();
-                                f.write_all(serde_json::to_string(&data).unwrap().as_bytes())
-                                    .await
-                                    .unwrap();
-                            }
-                            data
-                        }
-                    };
-                    let datetime: String = match e.metadata().await.and_then(|x| x.created()) {
-                        Ok(t) => DateTime::<Local>::from(t).to_rfc3339(),
-                        Err(_) => "1970-01-01T12:00:00".to_string(),
-                    };
-                    if data.visibility_status != TestrunVisibilityStatus::Hidden {
-                        res.push(Testrun {
-                            creation_date: datetime,
-                            name: e.file_name().to_owned().to_string_lossy().to_string(),
-                            progress: None,
-                            data: Some(data),
-                        })
-                    }
-                }
-            }
-            Ok(None) => break,
-            Err(_) => break,
+    for testrun in res.testruns.iter_mut() {
+        if testrun.name.starts_with("running-") {
+            let dir = state.result_dir.join(&testrun.name);
+            testrun.progress = state.live.stats(&dir).await.map(|s| s.started_users);
         }
     }
 
-    res.sort();
+    Ok(Json(res))
+}
 
-    let mut x = read_dir(&state.result_dir).await.unwrap();
-    loop {
-        match x.next_entry().await {
-            Ok(Some(e)) => {
-                if e.path().is_dir()
-                    && e.path()
-                        .file_name()
-                        .map(|e| e.to_string_lossy().starts_with("running-"))
-                        .unwrap_or(false)
-                {
-                    let mut sum = None;
-                    info!("{:?}", e.path());
-                    let data_file = e.path().join("testrun-data.json");
-                    {
-                        let mut x = read_dir(&e.path()).await.unwrap();
-                        loop {
-                            match x.next_entry().await {
-                                Ok(Some(e)) => {
-                                    if e.path().is_dir() {
-                                        let f = e.path().join("simulation.log");
-                                        if f.exists() {
-                                            sum = read_to_string(f).await.ok().map(|c| {
-                                                c.lines()
-                                                    .filter(|l| {
-                                                        l.contains("USER") && l.contains("START")
-                                                    })
-                                                    .count()
-                                                    as u64
-                                            });
-                                        }
-                                    }
-                                }
-                                Ok(None) => break,
-                                Err(_) => break,
-                            }
-                        }
-                    }
-                    if let Ok(d) = read_data_file(&data_file).await {
-                        res.push(Testrun {
-                            creation_date: "".into(),
-                            name: e.file_name().to_owned().to_string_lossy().to_string(),
-                            progress: sum,
-                            data: Some(d),
-                        })
-                    }
+/// Reads a testrun from its directory in the result dir.
+///
+/// The data of finished runs without a data file is taken from their
+/// `simulation.log`, the data file is only written once the run is changed.
+pub async fn read_testrun(dir: &path::Path) -> Option<Testrun> {
+    let name = dir.file_name()?.to_string_lossy().to_string();
+    let data_file = dir.join(DATA_FILE);
+
+    let data: TestrunData = match read_data_file(&data_file).await {
+        Ok(df) => df,
+        Err(_) if name.starts_with("running-") => return None,
+        Err(err) => {
+            warn!(
+                "Cannot read data file {:?} because of {:?}",
+                &data_file, err
+            );
+            let simulation_log_file = dir.join("simulation.log");
+            if simulation_log_file.exists() {
+                let f = std::fs::File::open(&simulation_log_file).ok()?;
+                let report = GatlingReport::from_file(&mut BufReader::new(&f)).ok();
+
+                TestrunData {
+                    statistics: report,
+                    ..Default::default()
                 }
+            } else {
+                Default::default()
             }
-            Ok(None) => break,
-            Err(_) => break,
         }
+    };
+
+    let mut data = data;
+    if data.id.is_empty() && !name.starts_with("running-") {
+        data.id = name.clone();
     }
 
-    res.reverse();
-    Ok(Json(res))
+    Some(Testrun {
+        creation_date: creation_date(dir, &data).await,
+        name,
+        progress: None,
+        data: Some(data),
+    })
+}
+
+/// The start of the run if it is known, otherwise the time its directory was
+/// created, or modified on filesystems without creation times.
+async fn creation_date(dir: &path::Path, data: &TestrunData) -> String {
+    if let Some(date) = data.start_date() {
+        return date.to_rfc3339();
+    }
+    match fs::metadata(dir)
+        .await
+        .and_then(|x| x.created().or_else(|_| x.modified()))
+    {
+        Ok(t) => DateTime::<Local>::from(t).to_rfc3339(),
+        Err(_) => DateTime::<Utc>::UNIX_EPOCH.to_rfc3339(),
+    }
+}
+
+/// The directory of the testrun `name`, which must not point outside of the
+/// result dir.
+pub fn testrun_dir(state: &AppState, name: &str) -> error::Result<PathBuf> {
+    if name.is_empty() || name.contains('/') || name.contains("..") {
+        return Err(Error::NotFound);
+    }
+    let dir = state.result_dir.join(name);
+    if !dir.is_dir() {
+        return Err(Error::NotFound);
+    }
+    Ok(dir)
+}
+
+/// Like `testrun_dir`, but refuses testruns which are still running.
+pub fn finished_testrun_dir(state: &AppState, name: &str) -> error::Result<PathBuf> {
+    if name.starts_with("running-") {
+        return Err(Error::BadRequest("Testrun is still running".into()));
+    }
+    testrun_dir(state, name)
+}
+
+/// Writes the data file of a testrun and updates the index accordingly.
+/// Runs from before ids were introduced get their directory name as id.
+pub async fn save_testrun(state: &AppState, dir: &path::Path, data: &TestrunData) -> error::Result<()> {
+    let name = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut data = data.clone();
+    if data.id.is_empty() && !name.starts_with("running-") {
+        data.id = name.clone();
+    }
+
+    write_data_file(&dir.join(DATA_FILE), &data).await?;
+
+    state.index.upsert(
+        &Testrun {
+            creation_date: creation_date(dir, &data).await,
+            name,
+            progress: None,
+            data: Some(data),
+        },
+        data_modified(dir),
+    )
+}
+
+pub async fn rebuild_index(State(state): State<Arc<AppState>>) -> error::Result<impl IntoResponse> {
+    state.index.rebuild(&state.result_dir).await?;
+    Ok((StatusCode::OK, "OK").into_response())
 }
 
 pub async fn get_testsuites(State(state): State<Arc<AppState>>) -> error::Result<Json<Vec<Testsuite>>> {
@@ -202,146 +239,595 @@ pub async fn get_testsuites(State(state): State<Arc<AppState>>) -> error::Result
     Ok(Json(res))
 }
 
-pub async fn update_visibility_status(
+pub async fn get_testrun(
+    Path(name): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> error::Result<Json<TestrunDetails>> {
+    let dir = testrun_dir(&state, &name)?;
+    let mut testrun = read_testrun(&dir).await.ok_or(Error::NotFound)?;
+    if name.starts_with("running-") {
+        testrun.progress = state.live.stats(&dir).await.map(|s| s.started_users);
+    }
+
+    let url = |file: &str| {
+        dir.join(file)
+            .exists()
+            .then(|| format!("/simulations/{}/{}", name, file))
+    };
+
+    let mut hooks = vec![];
+    for hook in testrun.data.iter().flat_map(|d| d.hooks.iter()) {
+        hooks.push(HookOutput {
+            result: hook.clone(),
+            url: format!("/simulations/{}/{}", name, hook.output_file),
+            output: read_to_string(dir.join(&hook.output_file))
+                .await
+                .unwrap_or_default(),
+        });
+    }
+
+    Ok(Json(TestrunDetails {
+        report_url: url("index.html"),
+        console_log_url: url(CONSOLE_LOG),
+        simulation_log_url: url("simulation.log"),
+        hooks,
+        testrun,
+    }))
+}
+
+pub async fn update_testrun(
     Path(name): Path<String>,
     State(state): State<Arc<AppState>>,
     param: Json<UpdateTestrunData>,
 ) -> error::Result<impl IntoResponse> {
-    let x = PathBuf::from(&state.result_dir).join(name);
+    let x = testrun_dir(&state, &name)?;
 
-    if !x.exists() {
-        return Err(Error::NotFound);
+    let mut d = read_testrun(&x)
+        .await
+        .and_then(|t| t.data)
+        .ok_or(Error::NotFound)?;
+    if let Some(ref visibility_status) = param.visibility_status {
+        d.visibility_status = visibility_status.clone();
+    }
+    if let Some(ref notes) = param.notes {
+        d.notes = notes.clone();
+    }
+    if let Some(ref tags) = param.tags {
+        if tags.iter().any(|t| t.contains(',')) {
+            return Err(Error::BadRequest("Tags must not contain commas".into()));
+        }
+        d.tags = tags
+            .iter()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+    }
+    if let Some(baseline) = param.baseline {
+        if baseline && d.statistics.is_none() {
+            return Err(Error::BadRequest(
+                "Testruns without statistics cannot be a baseline".into(),
+            ));
+        }
+        if baseline {
+            replace_baseline(&state, &d).await?;
+        }
+        d.baseline = baseline;
     }
 
-    let data_file = x.join("testrun-data.json");
+    save_testrun(&state, &x, &d).await?;
+    Ok((StatusCode::OK, "OK").into_response())
+}
 
-    if !x.exists() {
-        return Err(Error::NotFound);
+/// Removes the directory of a finished testrun and its index entry.
+pub async fn remove_testrun(state: &AppState, name: &str) -> error::Result<()> {
+    let dir = finished_testrun_dir(state, name)?;
+
+    remove_dir_all(&dir).await?;
+    state.index.remove(name)?;
+    Ok(())
+}
+
+pub async fn export_testrun(
+    Path(name): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> error::Result<impl IntoResponse> {
+    let dir = finished_testrun_dir(&state, &name)?;
+    let archive = pack_testrun(&dir, &name)?;
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/gzip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.tar.gz\"", name),
+            ),
+        ],
+        archive,
+    )
+        .into_response())
+}
+
+/// Registers a testrun exported by `export_testrun`, possibly on another
+/// instance. The archive is unpacked next to the result dir first, so that
+/// nothing half-imported shows up as testrun.
+pub async fn import_testrun(
+    State(state): State<Arc<AppState>>,
+    body: Bytes,
+) -> error::Result<Json<Testrun>> {
+    let import_dir = state
+        .result_dir
+        .with_file_name(format!("waterpistol-import-{}", Uuid::new_v4()));
+    create_dir_all(&import_dir).await?;
+
+    let result = import_unpacked(&state, &import_dir, &body).await;
+    if import_dir.exists() {
+        let _ = remove_dir_all(&import_dir).await;
     }
+    result
+}
 
-    let mut d = read_data_file(&data_file).await?;
-    d.visibility_status = param.visibility_status.as_ref().unwrap().clone();
+async fn import_unpacked(
+    state: &AppState,
+    import_dir: &path::Path,
+    archive: &[u8],
+) -> error::Result<Json<Testrun>> {
+    if unpack_stripped(archive, import_dir).is_err() {
+        return Err(Error::BadRequest("Not a tar.gz archive".into()));
+    }
 
-    {
-        let mut f = File::create(&data_file).await.unwrap();
-        f.write_all(serde_json::to_string(&d).unwrap().as_bytes())
-            .await
-            .unwrap();
+    let mut data = read_data_file(&import_dir.join(DATA_FILE))
+        .await
+        .map_err(|_| Error::BadRequest("Archive contains no valid testrun-data.json".into()))?;
+
+    if data.status == TestrunStatus::Running {
+        return Err(Error::BadRequest("Running testruns cannot be imported".into()));
     }
+    if data.statistics.is_none() && data.status != TestrunStatus::Failed {
+        return Err(Error::BadRequest("Testrun has no statistics".into()));
+    }
+
+    if data.id.is_empty() || !data.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        data.id = Uuid::new_v4().to_string();
+    }
+    if data.id.starts_with("running-") || RESERVED_IDS.contains(&data.id.as_str()) {
+        return Err(Error::BadRequest(format!(
+            "{} cannot be the id of a testrun",
+            data.id
+        )));
+    }
+    let target_test_dir = state.result_dir.join(&data.id);
+    if target_test_dir.exists() {
+        return Err(Error::Conflict(format!("Testrun {} already exists", data.id)));
+    }
+
+    // Sequence numbers are local to every instance.
+    data.sequence = state.index.next_sequence();
+    // Baselines are chosen per instance.
+    data.baseline = false;
+
+    info!(id = data.id, "Importing testrun");
+    rename(import_dir, &target_test_dir).await?;
+    save_testrun(state, &target_test_dir, &data).await?;
+
+    read_testrun(&target_test_dir)
+        .await
+        .map(Json)
+        .ok_or(Error::NotFound)
+}
+
+pub async fn delete_testrun(
+    Path(name): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> error::Result<impl IntoResponse> {
+    remove_testrun(&state, &name).await?;
     Ok((StatusCode::OK, "OK").into_response())
 }
 
+/// Directory of the run with the given id, whether it is still running or
+/// not.
+pub fn run_dir(state: &AppState, id: &str) -> error::Result<PathBuf> {
+    testrun_dir(state, &format!("running-{}", id)).or_else(|_| testrun_dir(state, id))
+}
+
+pub fn is_running(dir: &path::Path) -> bool {
+    dir.file_name()
+        .is_some_and(|n| n.to_string_lossy().starts_with("running-"))
+}
+
+/// Responds with the state of a run, waiting for it to finish if requested.
+async fn run_status(state: &AppState, id: &str, query: &RunQuery) -> error::Result<Response> {
+    let deadline =
+        Instant::now() + Duration::from_secs(query.timeout.unwrap_or(DEFAULT_WAIT_TIMEOUT));
+    let mut dir = run_dir(state, id)?;
+    while query.wait && is_running(&dir) && Instant::now() < deadline {
+        sleep(WAIT_INTERVAL).await;
+        dir = run_dir(state, id)?;
+    }
+
+    let testrun = read_testrun(&dir).await.ok_or(Error::NotFound)?;
+    let data = testrun.data.unwrap_or_default();
+    let running = is_running(&dir);
+
+    let status = RunStatus {
+        id: id.to_string(),
+        sequence: data.sequence,
+        status_url: format!("/api/testruns/{}/status", id),
+        verdict: (!running).then(|| data.verdict(&state.config().thresholds)),
+        status: data.status,
+    };
+    let code = if running {
+        StatusCode::ACCEPTED
+    } else {
+        StatusCode::OK
+    };
+    Ok((code, Json(status)).into_response())
+}
+
+pub async fn get_run_status(
+    Path(name): Path<String>,
+    Query(query): Query<RunQuery>,
+    State(state): State<Arc<AppState>>,
+) -> error::Result<Response> {
+    run_status(&state, &name, &query).await
+}
+
 pub async fn run_test(
+    Query(query): Query<RunQuery>,
     State(state): State<Arc<AppState>>,
     test_param: Json<RunTestParam>,
-) -> impl IntoResponse {
-    tokio::spawn(async move {
-        let app_config = &state.app_config;
+) -> error::Result<Response> {
+    info!("Starting simulation");
+
+    let uuid = Uuid::new_v4();
+    let uuid = format!("{}", uuid);
+    let started_at = Utc::now();
+    let sequence = state.index.next_sequence();
 
-        info!("Starting simulation");
+    {
+        let temp_test_dir = state.result_dir.join(format!("running-{}", uuid));
+        create_dir_all(&temp_test_dir).await?;
+
+        let data = TestrunData {
+            id: uuid.clone(),
+            sequence,
+            started_at: Some(started_at),
+            datum: Some(started_at),
+            status: TestrunStatus::Running,
+            custom_params: test_param.custom_params.clone(),
+            statistics: None,
+            ..Default::default()
+        };
+
+        save_testrun(&state, &temp_test_dir, &data).await?;
+        state.events.emit(ServerEvent::RunQueued {
+            id: uuid.clone(),
+            sequence,
+            custom_params: data.custom_params,
+        });
+    }
 
-        let uuid = Uuid::new_v4();
-        let uuid = format!("{}", uuid);
+    let run_state = state.clone();
+    let run_id = uuid.clone();
+    let run = async move {
+        let state = run_state;
+        let uuid = run_id;
+        let app_config = state.config();
 
         let target_test_dir = state.result_dir.join(&uuid);
-        let temp_test_dir = state.result_dir.join(&format!("running-{}", uuid));
+        let temp_name = format!("running-{}", uuid);
+        let temp_test_dir = state.result_dir.join(&temp_name);
+        let testsuite_dir = state.data_dir.join(TESTSUITE_NAME);
 
-        create_dir_all(&temp_test_dir).await.unwrap();
+        let mut hook_env = test_param.custom_params.clone();
+        hook_env.insert("WATERPISTOL_RUN_ID".into(), uuid.clone());
+        hook_env.insert("WATERPISTOL_DESCRIPTION".into(), test_param.description.clone());
+        hook_env.insert(
+            "WATERPISTOL_SIMULATION_CLASS".into(),
+            app_config.simulation.simulation_class.clone(),
+        );
 
-        {
-            let data = TestrunData {
-                datum: None,
-                status: TestrunStatus::Running,
-                custom_params: test_param.custom_params.clone(),
-                statistics: None,
-                ..Default::default()
+        let mut hook_results = vec![];
+
+        if let Some(ref command) = app_config.simulation.hooks.pre_run {
+            let result = run_hook(
+                HookKind::PreRun,
+                command,
+                &hook_env,
+                &testsuite_dir,
+                &temp_test_dir,
+            )
+            .await;
+            let success = result.success();
+            hook_results.push(result);
+
+            if !success {
+                warn!("Pre-run hook failed, aborting simulation");
+
+                create_dir_all(&target_test_dir).await.unwrap();
+                move_hook_outputs(&hook_results, &temp_test_dir, &target_test_dir).await;
+
+                let edited = edited_while_running(&temp_test_dir).await;
+                let data = TestrunData {
+                    id: uuid.clone(),
+                    sequence,
+                    started_at: Some(started_at),
+                    finished_at: Some(Utc::now()),
+                    datum: Some(started_at),
+                    status: TestrunStatus::Failed,
+                    custom_params: test_param.custom_params.clone(),
+                    hooks: hook_results,
+                    notes: edited.notes,
+                    tags: edited.tags,
+                    visibility_status: edited.visibility_status,
+                    ..Default::default()
+                };
+                save_testrun(&state, &target_test_dir, &data).await.unwrap();
+
+                remove_dir_all(&temp_test_dir).await.unwrap();
+                state.index.remove(&temp_name).unwrap();
+                state.live.remove(&temp_name);
+                announce_end(&state, &data);
+                return;
+            }
+        }
+
+        let params = simulation_params(&app_config, &test_param.custom_params);
+        state.events.emit(ServerEvent::RunStarted {
+            id: uuid.clone(),
+            sequence,
+        });
+
+        let report = if test_param.agents > 0 {
+            let job = AgentJob {
+                run_id: uuid.clone(),
+                testsuite: TESTSUITE_NAME.to_string(),
+                simulation_class: app_config.simulation.simulation_class.clone(),
+                description: test_param.description.clone(),
+                params,
             };
+            run_distributed(
+                &state,
+                test_param.agents,
+                job,
+                &testsuite_dir,
+                &target_test_dir,
+            )
+            .await
+        } else {
+            let console_log = std::fs::File::create(temp_test_dir.join(CONSOLE_LOG)).unwrap();
+            let output = gatling_command(
+                &testsuite_dir,
+                &app_config.simulation.simulation_class,
+                &test_param.description,
+                &params,
+                &temp_test_dir,
+            )
+            .stdout(console_log.try_clone().unwrap())
+            .stderr(console_log)
+            .status()
+            .await
+            .unwrap();
 
-            let mut f = File::create(temp_test_dir.join("testrun-data.json"))
-                .await
-                .unwrap();
-            f.write_all(serde_json::to_string(&data).unwrap().as_bytes())
+            info!(?output, "Output");
+
+            match find_result_dir(&temp_test_dir).await {
+                Some(dir) => {
+                    rename(dir, &target_test_dir).await.unwrap();
+
+                    let f = std::fs::File::open(target_test_dir.join("simulation.log")).unwrap();
+                    Some(GatlingReport::from_file(&mut BufReader::new(&f)).unwrap())
+                }
+                None => None,
+            }
+        };
+
+        // Without a gatling result folder the run is kept as failed, so that
+        // the hook outputs remain accessible.
+        if report.is_none() {
+            warn!("Simulation did not produce any results");
+            create_dir_all(&target_test_dir).await.unwrap();
+        }
+
+        move_hook_outputs(&hook_results, &temp_test_dir, &target_test_dir).await;
+        if temp_test_dir.join(CONSOLE_LOG).exists() {
+            rename(temp_test_dir.join(CONSOLE_LOG), target_test_dir.join(CONSOLE_LOG))
                 .await
                 .unwrap();
         }
 
-        let mut cmd = Command::new("mvn");
-
-        cmd.arg("gatling:test")
-            .arg(format!(
-                "-Dgatling.simulationClass={}",
-                app_config.simulation.simulation_class
-            ))
-            .arg(format!(
-                "-Dgatling.runDescription={}",
-                &test_param.description
-            ))
-            .arg(format!(
-                "-Dgatling.resultsFolder={}",
-                &temp_test_dir.as_os_str().to_string_lossy()
-            ));
+        if let Some(ref command) = app_config.simulation.hooks.post_run {
+            hook_env.insert(
+                "WATERPISTOL_RESULT_DIR".into(),
+                target_test_dir.to_string_lossy().to_string(),
+            );
+            hook_env.insert(
+                "WATERPISTOL_STATUS".into(),
+                if report.is_some() { "Done" } else { "Failed" }.into(),
+            );
 
-        for param in &app_config.simulation.params {
-            if let Some(v) = test_param.custom_params.get(&param.name) {
-                cmd.arg(format!("-D{}={}", param.name, v));
+            let result = run_hook(
+                HookKind::PostRun,
+                command,
+                &hook_env,
+                &testsuite_dir,
+                &target_test_dir,
+            )
+            .await;
+            if !result.success() {
+                warn!("Post-run hook failed");
             }
+            hook_results.push(result);
         }
 
-        cmd.current_dir(&state.data_dir.join(TESTSUITE_NAME));
-
-        let output = cmd.status().await.unwrap();
-
-        info!(?output, "Output");
-
-        let mut x = read_dir(&temp_test_dir).await.unwrap();
-
-        loop {
-            match x.next_entry().await {
-                Ok(Some(e)) => {
-                    if e.path().is_dir() {
-                        rename(e.path(), &target_test_dir).await.unwrap();
-
-                        let report = {
-                            let f = std::fs::File::open(target_test_dir.join("simulation.log"))
-                                .unwrap();
-                            GatlingReport::from_file(&mut BufReader::new(&f)).unwrap()
-                        };
-
-                        let data = TestrunData {
-                            datum: PathBuf::from(target_test_dir.join("simulation.log"))
-                                .metadata()
-                                .and_then(|m| m.created())
-                                .map(|t| DateTime::<Utc>::from(t))
-                                .ok(),
-                            status: TestrunStatus::Done,
-                            custom_params: test_param.custom_params.clone(),
-                            statistics: Some(report),
-                            ..Default::default()
-                        };
-
-                        {
-                            let mut f = File::create(target_test_dir.join("testrun-data.json"))
-                                .await
-                                .unwrap();
-                            f.write_all(serde_json::to_string(&data).unwrap().as_bytes())
-                                .await
-                                .unwrap();
-                        }
-
-                        break;
-                    }
-                }
-                Ok(None) => break,
-                Err(_) => break,
-            }
-        }
+        let edited = edited_while_running(&temp_test_dir).await;
+        let mut data = TestrunData {
+            id: uuid.clone(),
+            sequence,
+            started_at: Some(started_at),
+            finished_at: Some(Utc::now()),
+            datum: Some(started_at),
+            status: if report.is_some() {
+                TestrunStatus::Done
+            } else {
+                TestrunStatus::Failed
+            },
+            custom_params: test_param.custom_params.clone(),
+            statistics: report,
+            hooks: hook_results,
+            notes: edited.notes,
+            tags: edited.tags,
+            visibility_status: edited.visibility_status,
+            ..Default::default()
+        };
+        data.regression = compare_with_baseline(&state, &data);
+
+        save_testrun(&state, &target_test_dir, &data).await.unwrap();
 
         remove_dir_all(&temp_test_dir).await.unwrap();
+        state.index.remove(&temp_name).unwrap();
+        state.live.remove(&temp_name);
+        announce_end(&state, &data);
 
         info!("Simulation finished.")
-    });
+    };
+    tokio::spawn(supervise_run(state.clone(), uuid.clone(), run));
+
+    run_status(&state, &uuid, &query).await
+}
+
+/// Drives a testrun to its end. Should it crash, the run is marked as failed,
+/// so that it does not stay running forever and waiting clients get an answer.
+async fn supervise_run(
+    state: Arc<AppState>,
+    id: String,
+    run: impl Future<Output = ()> + Send + 'static,
+) {
+    if let Err(err) = tokio::spawn(run).await {
+        error!(id, ?err, "Testrun crashed");
+        if let Err(err) = fail_run(&state, &id).await {
+            error!(id, ?err, "Cannot mark crashed testrun as failed");
+        }
+    }
+}
+
+/// Finishes a crashed run: the files of its running directory are kept in
+/// its final one, which gets a failed data file unless the run got that far.
+async fn fail_run(state: &AppState, id: &str) -> error::Result<()> {
+    let temp_name = format!("running-{}", id);
+    let temp_test_dir = state.result_dir.join(&temp_name);
+    let target_test_dir = state.result_dir.join(id);
+    create_dir_all(&target_test_dir).await?;
+
+    if let Ok(mut entries) = read_dir(&temp_test_dir).await {
+        while let Some(e) = entries.next_entry().await? {
+            let target = target_test_dir.join(e.file_name());
+            if e.file_name() != DATA_FILE && !target.exists() {
+                rename(e.path(), target).await?;
+            }
+        }
+    }
+
+    let data = match read_data_file(&target_test_dir.join(DATA_FILE)).await {
+        Ok(data) => data,
+        Err(_) => {
+            let data = TestrunData {
+                finished_at: Some(Utc::now()),
+                status: TestrunStatus::Failed,
+                ..edited_while_running(&temp_test_dir).await
+            };
+            save_testrun(state, &target_test_dir, &data).await?;
+            data
+        }
+    };
+
+    if temp_test_dir.exists() {
+        remove_dir_all(&temp_test_dir).await?;
+    }
+    state.index.remove(&temp_name)?;
+    state.live.remove(&temp_name);
+    announce_end(state, &data);
+    Ok(())
+}
+
+pub async fn merge_simulation_logs(
+    State(state): State<Arc<AppState>>,
+    upload: Json<MergeSimulationLogs>,
+) -> error::Result<impl IntoResponse> {
+    if upload.logs.is_empty() {
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, "No simulation logs given").into_response());
+    }
+
+    let uuid = format!("{}", Uuid::new_v4());
+    let target_test_dir = state.result_dir.join(&uuid);
+
+    let mut logs = vec![];
+    for (i, log) in upload.logs.iter().enumerate() {
+        info!("Storing uploaded simulation log {}", log.file_name);
+        let injector_dir = target_test_dir.join("injectors").join(i.to_string());
+        create_dir_all(&injector_dir).await?;
+        let log_file = injector_dir.join("simulation.log");
+        {
+            let mut f = File::create(&log_file).await?;
+            f.write_all(&log.data).await?;
+        }
+        logs.push(log_file);
+    }
+
+    let testsuite_dir = state.data_dir.join(TESTSUITE_NAME);
+    let Some(report) = merge_injector_logs(&testsuite_dir, &target_test_dir, &logs).await else {
+        remove_dir_all(&target_test_dir).await?;
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, "Simulation logs cannot be parsed").into_response());
+    };
+
+    let mut data = TestrunData {
+        id: uuid.clone(),
+        sequence: state.index.next_sequence(),
+        started_at: DateTime::from_timestamp_millis(report.start_time as i64),
+        finished_at: DateTime::from_timestamp_millis(report.end_time as i64),
+        datum: Some(Utc::now()),
+        status: TestrunStatus::Done,
+        custom_params: upload.custom_params.clone(),
+        statistics: Some(report),
+        ..Default::default()
+    };
+    data.regression = compare_with_baseline(&state, &data);
+    save_testrun(&state, &target_test_dir, &data).await?;
+    announce_end(&state, &data);
+
+    Ok(Json(Testrun {
+        creation_date: creation_date(&target_test_dir, &data).await,
+        name: uuid,
+        progress: None,
+        data: Some(data),
+    })
+    .into_response())
+}
 
-    "Ok"
+/// The data of a running testrun, which holds the notes, tags and
+/// visibility edited while it was going.
+async fn edited_while_running(temp_test_dir: &path::Path) -> TestrunData {
+    read_data_file(&temp_test_dir.join(DATA_FILE))
+        .await
+        .unwrap_or_default()
+}
+
+/// Tells the clients and the webhooks that a run ended.
+fn announce_end(state: &AppState, data: &TestrunData) {
+    state.events.emit(run_ended(data, &state.config().thresholds));
+    notify(state, data);
+}
+
+/// Moves the captured hook outputs from the temporary run directory to the
+/// final one.
+async fn move_hook_outputs(hooks: &[HookResult], from: &path::Path, to: &path::Path) {
+    for hook in hooks {
+        let source = from.join(&hook.output_file);
+        if source.exists() {
+            if let Err(err) = rename(&source, to.join(&hook.output_file)).await {
+                warn!(?err, "Cannot move hook output {:?}", source);
+            }
+        }
+    }
 }
 
 pub async fn upload_archive(
@@ -350,40 +836,220 @@ pub async fn upload_archive(
 ) -> error::Result<impl IntoResponse> {
     let f = state.data_dir.join("tempfile.tar.gz");
 
-    let file_name = TESTSUITE_NAME;
     let mime_type = upload.mime_type.clone();
     let unpack_dir = state.data_dir.join(TESTSUITE_NAME);
 
     if mime_type == "application/gzip" || mime_type == "application/x-gzip" {
-    {
-        let mut file = File::create(&f).await?;
-        file.write(&upload.data).await?;
-        file.flush().await?;
-    }
+        {
+            let mut file = File::create(&f).await?;
+            file.write_all(&upload.data).await?;
+            file.flush().await?;
+        }
 
-    {
-        let tar_gz = std::fs::File::open(&f)?;
-        let tar = GzDecoder::new(tar_gz);
-        let mut archive = Archive::new(tar);
-        archive
-        .entries()?
-        .filter_map(|e| e.ok())
-        .map(|mut entry| -> Result<PathBuf> {
-            let path = entry.path()?;
-            let mut components = path.components();
-            components.next();
-            let path = unpack_dir.join(components.as_path());
-            entry.unpack(&path)?;
-            Ok(path)
-        })
-        .filter_map(|e| e.ok())
-        .for_each(|x| println!("> {}", x.display()));
-    }
-
-    let _ = fs::remove_file(&f).await;
-
-    Ok((StatusCode::OK, "").into_response())
-} else {
-    Ok((StatusCode::UNPROCESSABLE_ENTITY, format!("Archive was not a proper archive. Mime type was {}, need application/gzip.", mime_type)).into_response())
+        {
+            let tar_gz = std::fs::File::open(&f)?;
+            let unpacked = unpack_stripped(tar_gz, &unpack_dir)?;
+            info!(files = unpacked.len(), "Unpacked testsuite");
+        }
+
+        let _ = fs::remove_file(&f).await;
+        state.events.emit(ServerEvent::SuiteUploaded {
+            name: TESTSUITE_NAME.to_string(),
+        });
+
+        Ok((StatusCode::OK, "").into_response())
+    } else {
+        Ok((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!(
+                "Archive was not a proper archive. Mime type was {}, need application/gzip.",
+                mime_type
+            ),
+        )
+            .into_response())
+    }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use axum::body::{to_bytes, Bytes};
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use models::config::AppConfig;
+    use models::report::{TestrunData, TestrunStatus};
+    use models::{RunQuery, RunStatus, Testrun};
+    use tar::{Builder, EntryType, Header};
+    use tempfile::tempdir;
+    use tokio::fs::create_dir_all;
+    use tokio::time::sleep;
+
+    use crate::archive::pack_testrun;
+    use crate::controller::{
+        import_testrun, read_data_file, run_status, save_testrun, supervise_run, DATA_FILE,
+    };
+    use crate::error::{self, Error};
+    use crate::test_util::finished;
+    use crate::AppState;
+
+    /// An exported testrun with `data` and a simulation log.
+    fn exported(data: &TestrunData) -> Bytes {
+        let tmp = tempdir().unwrap();
+        let json = serde_json::to_vec(data).unwrap();
+        std::fs::write(tmp.path().join(DATA_FILE), json).unwrap();
+        std::fs::write(tmp.path().join("simulation.log"), "log").unwrap();
+        pack_testrun(tmp.path(), &data.id).unwrap().into()
+    }
+
+    async fn import(state: &Arc<AppState>, archive: Bytes) -> error::Result<Testrun> {
+        create_dir_all(&state.result_dir).await.unwrap();
+        import_testrun(State(state.clone()), archive)
+            .await
+            .map(|t| t.0)
+    }
+
+    #[tokio::test]
+    async fn exported_testrun_is_imported() {
+        let tmp = tempdir().unwrap();
+        let state = AppState::for_tests(tmp.path(), AppConfig::default());
+        let data = TestrunData {
+            sequence: 42,
+            baseline: true,
+            ..finished("abc", "Sim", &[("home", 10, true)])
+        };
+
+        let testrun = import(&state, exported(&data)).await.unwrap();
+
+        assert_eq!(testrun.name, "abc");
+        let imported = testrun.data.unwrap();
+        assert_eq!(imported.sequence, 1);
+        assert!(!imported.baseline);
+        assert!(state.result_dir.join("abc").join("simulation.log").exists());
+        assert_eq!(state.index.all().unwrap()[0].name, "abc");
+
+        let again = import(&state, exported(&data)).await;
+        assert!(matches!(again, Err(Error::Conflict(_))), "{:?}", again);
+    }
+
+    #[tokio::test]
+    async fn unfinished_and_misnamed_testruns_are_not_imported() {
+        let tmp = tempdir().unwrap();
+        let state = AppState::for_tests(tmp.path(), AppConfig::default());
+        let running = TestrunData {
+            status: TestrunStatus::Running,
+            ..finished("abc", "Sim", &[])
+        };
+        let without_statistics = TestrunData {
+            statistics: None,
+            ..finished("abc", "Sim", &[])
+        };
+
+        for data in [
+            running,
+            without_statistics,
+            finished("running-abc", "Sim", &[]),
+            finished("compare", "Sim", &[]),
+            finished("import", "Sim", &[]),
+        ] {
+            let result = import(&state, exported(&data)).await;
+            assert!(matches!(result, Err(Error::BadRequest(_))), "{:?}", result);
+        }
+        let result = import(&state, Bytes::from("not an archive")).await;
+        assert!(matches!(result, Err(Error::BadRequest(_))), "{:?}", result);
+
+        assert!(state.index.all().unwrap().is_empty());
+        assert_eq!(std::fs::read_dir(&state.result_dir).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn archive_entries_cannot_leave_the_testrun_dir() {
+        let tmp = tempdir().unwrap();
+        let state = AppState::for_tests(tmp.path(), AppConfig::default());
+        let data = serde_json::to_vec(&finished("abc", "Sim", &[])).unwrap();
+
+        let mut builder = Builder::new(GzEncoder::new(vec![], Compression::default()));
+        let mut header = Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, format!("abc/{}", DATA_FILE), data.as_slice())
+            .unwrap();
+        // `set_path` refuses `..`, so the name is written directly.
+        let mut header = Header::new_gnu();
+        let path = b"abc/../escaped.txt";
+        header.as_old_mut().name[..path.len()].copy_from_slice(path);
+        header.set_size(3);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, b"bad".as_slice()).unwrap();
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o777);
+        builder
+            .append_link(&mut header, "abc/link", tmp.path())
+            .unwrap();
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+
+        let testrun = import(&state, archive.into()).await.unwrap();
+
+        assert_eq!(testrun.name, "abc");
+        let dir = state.result_dir.join("abc");
+        assert!(dir.join(DATA_FILE).exists());
+        assert!(!dir.join("link").exists());
+        assert!(!state.result_dir.join("escaped.txt").exists());
+        assert!(!tmp.path().join("escaped.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn crashed_run_is_reported_as_failed_to_waiting_clients() {
+        let tmp = tempdir().unwrap();
+        let state = AppState::for_tests(tmp.path(), AppConfig::default());
+        let running = state.result_dir.join("running-abc");
+        create_dir_all(&running).await.unwrap();
+        let data = TestrunData {
+            id: "abc".into(),
+            sequence: 1,
+            status: TestrunStatus::Running,
+            notes: "edited while running".into(),
+            ..Default::default()
+        };
+        save_testrun(&state, &running, &data).await.unwrap();
+
+        let query = RunQuery {
+            wait: false,
+            timeout: None,
+        };
+        let response = run_status(&state, "abc", &query).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        tokio::spawn(supervise_run(state.clone(), "abc".into(), async {
+            sleep(Duration::from_millis(100)).await;
+            panic!("Cannot parse simulation.log");
+        }));
+
+        let query = RunQuery {
+            wait: true,
+            timeout: Some(10),
+        };
+        let response = run_status(&state, "abc", &query).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let status: RunStatus = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status.sequence, 1);
+        assert_eq!(status.status, TestrunStatus::Failed);
+        assert!(!status.verdict.unwrap().passed);
+
+        assert!(!running.exists());
+        let data = read_data_file(&state.result_dir.join("abc").join(DATA_FILE))
+            .await
+            .unwrap();
+        assert_eq!(data.notes, "edited while running");
+        assert!(state.index.all().unwrap().iter().all(|t| t.name == "abc"));
+    }
 }
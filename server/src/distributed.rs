@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::extract::{Path as UrlPath, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use models::report::GatlingReport;
+use models::{AgentInfo, AgentJob, AgentResultQuery};
+use tokio::fs::{create_dir_all, File};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{timeout, timeout_at, Instant};
+use tracing::{info, warn};
+
+use crate::archive::pack_testsuite;
+use crate::error::{self, Error};
+use crate::gatling::merge_injector_logs;
+use crate::AppState;
+
+/// How long an agent poll is held open while waiting for a job.
+const POLL_TIMEOUT: Duration = Duration::from_secs(25);
+/// Agents not polling within this time are considered gone.
+const AGENT_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long the controller waits for all agents to deliver their simulation
+/// logs.
+const RESULT_TIMEOUT: Duration = Duration::from_secs(6 * 60 * 60);
+
+pub enum AgentResult {
+    Done(Bytes),
+    Failed(String),
+}
+
+struct Agent {
+    last_seen: DateTime<Utc>,
+    busy: bool,
+    /// Job handed out by `poll` whose result is still outstanding.
+    handed_out: Option<AgentJob>,
+    jobs: mpsc::UnboundedSender<AgentJob>,
+    queue: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<AgentJob>>>,
+}
+
+/// Injector agents known to the controller, together with the runs they are
+/// currently working on.
+#[derive(Default)]
+pub struct AgentRegistry {
+    agents: Mutex<HashMap<String, Agent>>,
+    pending: Mutex<HashMap<(String, String), oneshot::Sender<AgentResult>>>,
+}
+
+impl AgentRegistry {
+    /// Registers the agent if necessary and marks it as seen, returns its
+    /// job queue.
+    fn register(&self, name: &str) -> Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<AgentJob>>> {
+        let mut agents = self.agents.lock().unwrap();
+        let agent = agents.entry(name.to_string()).or_insert_with(|| {
+            info!(name, "Agent registered");
+            let (jobs, queue) = mpsc::unbounded_channel();
+            Agent {
+                last_seen: Utc::now(),
+                busy: false,
+                handed_out: None,
+                jobs,
+                queue: Arc::new(tokio::sync::Mutex::new(queue)),
+            }
+        });
+        agent.last_seen = Utc::now();
+        agent.queue.clone()
+    }
+
+    /// Waits for the next job of the agent, registering it if necessary.
+    /// Agents don't poll while working on a job, so a job handed out before
+    /// without a result never reached the agent and is handed out again.
+    pub async fn poll(&self, name: &str) -> Option<AgentJob> {
+        let queue = self.register(name);
+        if let Some(job) = self.handed_out(name) {
+            warn!(name, run_id = job.run_id, "Handing out job again");
+            return Some(job);
+        }
+
+        let mut queue = queue.lock().await;
+        let job = timeout(POLL_TIMEOUT, queue.recv()).await.ok().flatten();
+        if let Some(agent) = self.agents.lock().unwrap().get_mut(name) {
+            agent.handed_out.clone_from(&job);
+        }
+        job
+    }
+
+    fn handed_out(&self, name: &str) -> Option<AgentJob> {
+        let agents = self.agents.lock().unwrap();
+        agents.get(name).and_then(|a| a.handed_out.clone())
+    }
+
+    /// Hands `job` to `count` idle agents.
+    pub fn dispatch(
+        &self,
+        count: u32,
+        job: &AgentJob,
+    ) -> std::result::Result<Vec<(String, oneshot::Receiver<AgentResult>)>, String> {
+        let mut agents = self.agents.lock().unwrap();
+        let mut pending = self.pending.lock().unwrap();
+
+        let alive_since = Utc::now() - AGENT_TIMEOUT;
+        let mut idle: Vec<&String> = agents
+            .iter()
+            .filter(|(_, a)| !a.busy && a.last_seen > alive_since)
+            .map(|(name, _)| name)
+            .collect();
+        idle.sort();
+
+        if idle.len() < count as usize {
+            return Err(format!(
+                "{} agents requested, but only {} available",
+                count,
+                idle.len()
+            ));
+        }
+
+        let names: Vec<String> = idle.into_iter().take(count as usize).cloned().collect();
+        // Nothing is handed out unless all agents can take the job, so that
+        // no agent stays busy with a run which never starts.
+        if let Some(name) = names.iter().find(|n| agents[*n].jobs.is_closed()) {
+            return Err(format!("Agent {} is not reachable", name));
+        }
+
+        let mut receivers: Vec<(String, oneshot::Receiver<AgentResult>)> = vec![];
+        for name in names {
+            let agent = agents.get_mut(&name).unwrap();
+            if agent.jobs.send(job.clone()).is_err() {
+                for (name, _) in &receivers {
+                    agents.get_mut(name).unwrap().busy = false;
+                    pending.remove(&(name.clone(), job.run_id.clone()));
+                }
+                return Err(format!("Agent {} is not reachable", name));
+            }
+            agent.busy = true;
+
+            let (tx, rx) = oneshot::channel();
+            pending.insert((name.clone(), job.run_id.clone()), tx);
+            receivers.push((name, rx));
+        }
+
+        Ok(receivers)
+    }
+
+    /// Delivers the result of a job, returns false if nobody waits for it.
+    pub fn complete(&self, name: &str, run_id: &str, result: AgentResult) -> bool {
+        if let Some(agent) = self.agents.lock().unwrap().get_mut(name) {
+            agent.busy = false;
+            agent.handed_out = None;
+        }
+
+        match self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(&(name.to_string(), run_id.to_string()))
+        {
+            Some(tx) => tx.send(result).is_ok(),
+            None => false,
+        }
+    }
+
+    pub fn list(&self) -> Vec<AgentInfo> {
+        let mut res: Vec<AgentInfo> = self
+            .agents
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, a)| AgentInfo {
+                name: name.clone(),
+                last_seen: a.last_seen,
+                busy: a.busy,
+            })
+            .collect();
+        res.sort_by(|a, b| a.name.cmp(&b.name));
+        res
+    }
+}
+
+/// Fans a run out to `count` agents and merges their simulation logs into
+/// `target_test_dir`. Returns the merged report if all agents succeeded.
+pub async fn run_distributed(
+    state: &AppState,
+    count: u32,
+    job: AgentJob,
+    testsuite_dir: &Path,
+    target_test_dir: &Path,
+) -> Option<GatlingReport> {
+    create_dir_all(target_test_dir).await.unwrap();
+
+    let receivers = match state.agents.dispatch(count, &job) {
+        Ok(r) => r,
+        Err(err) => {
+            warn!(err, "Cannot distribute simulation");
+            return None;
+        }
+    };
+
+    let mut logs = vec![];
+    let mut success = true;
+
+    // All agents run at the same time, so they share one deadline.
+    let deadline = Instant::now() + RESULT_TIMEOUT;
+    for (name, rx) in receivers {
+        match timeout_at(deadline, rx).await {
+            Ok(Ok(AgentResult::Done(log))) => {
+                info!(name, "Agent finished simulation");
+                let agent_dir = target_test_dir.join("injectors").join(&name);
+                create_dir_all(&agent_dir).await.unwrap();
+                let log_file = agent_dir.join("simulation.log");
+                {
+                    let mut f = File::create(&log_file).await.unwrap();
+                    f.write_all(&log).await.unwrap();
+                }
+                logs.push(log_file);
+            }
+            Ok(Ok(AgentResult::Failed(err))) => {
+                warn!(name, err, "Agent failed simulation");
+                success = false;
+            }
+            _ => {
+                warn!(name, "Agent did not deliver a result");
+                state
+                    .agents
+                    .complete(&name, &job.run_id, AgentResult::Failed("".into()));
+                success = false;
+            }
+        }
+    }
+
+    if !success || logs.is_empty() {
+        return None;
+    }
+
+    merge_injector_logs(testsuite_dir, target_test_dir, &logs).await
+}
+
+pub async fn get_agents(State(state): State<Arc<AppState>>) -> error::Result<Json<Vec<AgentInfo>>> {
+    Ok(Json(state.agents.list()))
+}
+
+pub async fn poll_job(
+    UrlPath(name): UrlPath<String>,
+    State(state): State<Arc<AppState>>,
+) -> error::Result<Json<Option<AgentJob>>> {
+    Ok(Json(state.agents.poll(&name).await))
+}
+
+pub async fn post_result(
+    UrlPath((name, run_id)): UrlPath<(String, String)>,
+    Query(query): Query<AgentResultQuery>,
+    State(state): State<Arc<AppState>>,
+    body: Bytes,
+) -> error::Result<impl IntoResponse> {
+    let result = if query.success {
+        AgentResult::Done(body)
+    } else {
+        AgentResult::Failed(String::from_utf8_lossy(&body).to_string())
+    };
+
+    // The agent has to learn that its result was dropped, e.g. because the
+    // controller gave up waiting, so this is not a 204 like unknown testruns.
+    if state.agents.complete(&name, &run_id, result) {
+        Ok((StatusCode::OK, "OK").into_response())
+    } else {
+        Err(Error::Conflict(format!(
+            "Nobody waits for the result of run {} from agent {}",
+            run_id, name
+        )))
+    }
+}
+
+pub async fn get_testsuite_archive(
+    UrlPath(name): UrlPath<String>,
+    State(state): State<Arc<AppState>>,
+) -> error::Result<impl IntoResponse> {
+    if name.contains('/') || name.contains("..") {
+        return Err(Error::NotFound);
+    }
+
+    let testsuite_dir = state.data_dir.join(&name);
+    if !testsuite_dir.is_dir() {
+        return Err(Error::NotFound);
+    }
+
+    let archive = pack_testsuite(&testsuite_dir, &name)?;
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/gzip")],
+        archive,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Bytes;
+    use chrono::Utc;
+    use models::AgentJob;
+
+    use crate::distributed::{AgentRegistry, AgentResult, AGENT_TIMEOUT};
+
+    fn job() -> AgentJob {
+        AgentJob {
+            run_id: "run-1".into(),
+            testsuite: "main".into(),
+            simulation_class: "Sim".into(),
+            description: "test".into(),
+            params: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn job_is_dispatched_and_completed() {
+        let registry = Arc::new(AgentRegistry::default());
+        registry.register("a");
+        registry.register("b");
+
+        let receivers = registry.dispatch(1, &job()).unwrap();
+        assert_eq!(receivers.len(), 1);
+        let (name, rx) = receivers.into_iter().next().unwrap();
+        assert_eq!(name, "a");
+        assert!(registry.list()[0].busy);
+        assert!(!registry.list()[1].busy);
+
+        let polled = registry.poll("a").await.unwrap();
+        assert_eq!(polled.run_id, "run-1");
+
+        assert!(registry.complete("a", "run-1", AgentResult::Done(Bytes::from("log"))));
+        assert!(matches!(rx.await, Ok(AgentResult::Done(log)) if log == "log"));
+        assert!(!registry.list()[0].busy);
+
+        // A late second delivery finds nobody waiting.
+        assert!(!registry.complete("a", "run-1", AgentResult::Failed("late".into())));
+    }
+
+    #[tokio::test]
+    async fn job_is_handed_out_again_until_its_result_arrives() {
+        let registry = AgentRegistry::default();
+        registry.register("a");
+        let (_, rx) = registry.dispatch(1, &job()).unwrap().pop().unwrap();
+
+        // The response to the first poll got lost on the way to the agent.
+        assert_eq!(registry.poll("a").await.unwrap().run_id, "run-1");
+        assert_eq!(registry.poll("a").await.unwrap().run_id, "run-1");
+
+        assert!(registry.complete("a", "run-1", AgentResult::Done(Bytes::from("log"))));
+        assert!(rx.await.is_ok());
+        assert!(registry.handed_out("a").is_none());
+    }
+
+    #[test]
+    fn busy_and_stale_agents_are_not_used() {
+        let registry = AgentRegistry::default();
+        registry.register("a");
+        registry.register("b");
+        registry.register("c");
+        {
+            let mut agents = registry.agents.lock().unwrap();
+            agents.get_mut("c").unwrap().last_seen = Utc::now() - AGENT_TIMEOUT * 2;
+        }
+
+        assert!(registry.dispatch(3, &job()).is_err());
+        assert!(registry.list().iter().all(|a| !a.busy));
+
+        registry.dispatch(1, &job()).unwrap();
+        let err = registry.dispatch(2, &job()).err().unwrap();
+        assert_eq!(err, "2 agents requested, but only 1 available");
+    }
+}
@@ -11,10 +11,16 @@ pub enum Error {
     Forbidden,
     #[error("Not Found")]
     NotFound,
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
     #[error("io error {0}")]
     Io(#[from] io::Error),
     #[error("serde error {0}")]
     Serde(#[from] serde_json::Error),
+    #[error("index error {0}")]
+    Index(#[from] rusqlite::Error),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -24,10 +30,13 @@ impl IntoResponse for Error {
         let msg = self.to_string();
         let status_code = match self {
             Self::NotFound => StatusCode::NO_CONTENT,
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Conflict(_) => StatusCode::CONFLICT,
             Self::Unauthorized => StatusCode::UNAUTHORIZED,
             Self::Forbidden => StatusCode::FORBIDDEN,
             Self::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::Serde(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Index(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
         (status_code, msg).into_response()
     }
@@ -0,0 +1,145 @@
+use std::convert::Infallible;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::stream::{self, Stream};
+use models::config::Threshold;
+use models::event::ServerEvent;
+use models::report::{TestrunData, TestrunStatus};
+use tokio::fs::read_dir;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{info, warn};
+
+use crate::live::LIVE_INTERVAL;
+use crate::AppState;
+
+/// Number of events a slow client may fall behind before it misses some.
+const EVENT_BUFFER: usize = 256;
+
+/// How often `waterpistol.yml` is checked for changes.
+const CONFIG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Distributes the server events to the connected clients.
+pub struct EventBus {
+    sender: broadcast::Sender<ServerEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus {
+            sender: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+}
+
+impl EventBus {
+    /// Sends an event to all clients, it is dropped if nobody listens.
+    pub fn emit(&self, event: ServerEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    fn has_clients(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+}
+
+/// `RunFinished` for a run with results, `RunFailed` otherwise.
+pub fn run_ended(data: &TestrunData, thresholds: &[Threshold]) -> ServerEvent {
+    match data.status {
+        TestrunStatus::Done => ServerEvent::RunFinished {
+            id: data.id.clone(),
+            sequence: data.sequence,
+            verdict: data.verdict(thresholds),
+        },
+        _ => ServerEvent::RunFailed {
+            id: data.id.clone(),
+            sequence: data.sequence,
+        },
+    }
+}
+
+/// Stream of all server events, see `ServerEvent`.
+pub async fn get_events(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.events.sender.subscribe();
+
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let sse = Event::default()
+                        .event(event.name())
+                        .json_data(&event)
+                        .unwrap_or_default();
+                    return Some((Ok(sse), receiver));
+                }
+                Err(RecvError::Lagged(missed)) => warn!("Client missed {} events", missed),
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// Sends the progress of the running testruns as long as there are clients.
+pub async fn run_progress_events(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(LIVE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if !state.events.has_clients() {
+            continue;
+        }
+
+        let Ok(mut entries) = read_dir(&state.result_dir).await else {
+            continue;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(id) = name.strip_prefix("running-") else {
+                continue;
+            };
+            if let Some(stats) = state.live.stats(&entry.path()).await {
+                state.events.emit(ServerEvent::RunProgress {
+                    id: id.to_string(),
+                    stats,
+                });
+            }
+        }
+    }
+}
+
+fn modified(file: &Path) -> Option<SystemTime> {
+    file.metadata().and_then(|m| m.modified()).ok()
+}
+
+/// Reloads `waterpistol.yml` whenever it changes. A config which cannot be
+/// read is ignored and the previous one is kept.
+pub async fn watch_config(state: Arc<AppState>) {
+    let file = Path::new("waterpistol.yml");
+    let mut last_modified = modified(file);
+
+    let mut interval = tokio::time::interval(CONFIG_INTERVAL);
+    loop {
+        interval.tick().await;
+        let current = modified(file);
+        if current == last_modified {
+            continue;
+        }
+        last_modified = current;
+
+        match crate::load_config() {
+            Ok(config) if config != *state.config() => {
+                info!("Configuration changed, reloaded waterpistol.yml");
+                state.set_config(config.clone());
+                state.events.emit(ServerEvent::ConfigChanged { config });
+            }
+            Ok(_) => (),
+            Err(err) => warn!("Cannot reload waterpistol.yml: {}", err),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use models::config::AppConfig;
+use models::report::{merge_logs, GatlingReport};
+use tokio::fs::read_dir;
+use tokio::process::Command;
+use tracing::warn;
+
+/// Builds the `mvn gatling:test` invocation for a simulation. Gatling will
+/// create a sub directory of `results_dir` for the run.
+pub fn gatling_command(
+    testsuite_dir: &Path,
+    simulation_class: &str,
+    description: &str,
+    params: &HashMap<String, String>,
+    results_dir: &Path,
+) -> Command {
+    let mut cmd = Command::new("mvn");
+
+    cmd.arg("gatling:test")
+        .arg(format!("-Dgatling.simulationClass={}", simulation_class))
+        .arg(format!("-Dgatling.runDescription={}", description))
+        .arg(format!(
+            "-Dgatling.resultsFolder={}",
+            results_dir.as_os_str().to_string_lossy()
+        ));
+
+    for (name, value) in params {
+        cmd.arg(format!("-D{}={}", name, value));
+    }
+
+    cmd.current_dir(testsuite_dir);
+    cmd
+}
+
+/// Builds a `mvn gatling:test` invocation which only generates the HTML
+/// report for the simulation log(s) in `run_dir`.
+pub fn reports_only_command(testsuite_dir: &Path, run_dir: &Path) -> Command {
+    let mut cmd = Command::new("mvn");
+
+    if let (Some(results_dir), Some(run_name)) = (run_dir.parent(), run_dir.file_name()) {
+        cmd.arg("gatling:test")
+            .arg(format!(
+                "-Dgatling.resultsFolder={}",
+                results_dir.as_os_str().to_string_lossy()
+            ))
+            .arg(format!(
+                "-Dgatling.reportsOnly={}",
+                run_name.to_string_lossy()
+            ));
+    }
+
+    cmd.current_dir(testsuite_dir);
+    cmd
+}
+
+/// The custom params of a run which are passed on to gatling, i.e. the ones
+/// that are part of the simulation config.
+pub fn simulation_params(
+    app_config: &AppConfig,
+    custom_params: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    app_config
+        .simulation
+        .params
+        .iter()
+        .filter_map(|p| {
+            custom_params
+                .get(&p.name)
+                .map(|v| (p.name.clone(), v.clone()))
+        })
+        .collect()
+}
+
+/// Returns the directory gatling created for a run inside `results_dir`.
+pub async fn find_result_dir(results_dir: &Path) -> Option<PathBuf> {
+    let mut x = read_dir(results_dir).await.ok()?;
+
+    loop {
+        match x.next_entry().await {
+            Ok(Some(e)) => {
+                if e.path().is_dir() {
+                    return Some(e.path());
+                }
+            }
+            Ok(None) => return None,
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Merges the simulation logs of several injectors into the `simulation.log`
+/// of `run_dir` and generates the HTML report for it.
+pub async fn merge_injector_logs(
+    testsuite_dir: &Path,
+    run_dir: &Path,
+    logs: &[PathBuf],
+) -> Option<GatlingReport> {
+    let merged_log = run_dir.join("simulation.log");
+
+    if let Err(err) = write_merged_log(logs, &merged_log) {
+        warn!(?err, "Cannot merge simulation logs");
+        return None;
+    }
+
+    match reports_only_command(testsuite_dir, run_dir).status().await {
+        Ok(status) if status.success() => (),
+        output => warn!(?output, "Cannot generate report for merged simulation logs"),
+    }
+
+    let f = File::open(&merged_log).ok()?;
+    GatlingReport::from_file(&mut BufReader::new(&f)).ok()
+}
+
+fn write_merged_log(logs: &[PathBuf], target: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut readers = logs
+        .iter()
+        .map(|l| File::open(l).map(BufReader::new))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    let mut streams: Vec<&mut dyn BufRead> =
+        readers.iter_mut().map(|r| r as &mut dyn BufRead).collect();
+    let mut out = BufWriter::new(File::create(target)?);
+    merge_logs(&mut streams, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use models::report::{HookKind, HookResult};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+/// Name of the file the output of a hook is captured to.
+pub fn file_name(kind: &HookKind) -> String {
+    match kind {
+        HookKind::PreRun => "pre-run-hook.log",
+        HookKind::PostRun => "post-run-hook.log",
+    }
+    .to_string()
+}
+
+/// Runs a hook command via `bash -c` in `working_dir`.
+///
+/// Stdout and stderr are captured into a log file in `output_dir`, the
+/// returned [`HookResult`] references that file by name.
+pub async fn run_hook(
+    kind: HookKind,
+    command: &str,
+    env: &HashMap<String, String>,
+    working_dir: &Path,
+    output_dir: &Path,
+) -> HookResult {
+    let output_file = file_name(&kind);
+
+    info!(?kind, command, "Running hook");
+
+    let output = Command::new("bash")
+        .arg("-c")
+        .arg(command)
+        .envs(env)
+        .current_dir(working_dir)
+        .output()
+        .await;
+
+    let (exit_code, log) = match output {
+        Ok(output) => {
+            let mut log = output.stdout;
+            log.extend_from_slice(&output.stderr);
+            (output.status.code(), log)
+        }
+        Err(err) => {
+            warn!(?kind, ?err, "Cannot execute hook");
+            (None, format!("Cannot execute hook: {}", err).into_bytes())
+        }
+    };
+
+    if let Err(err) = write_log(&output_dir.join(&output_file), &log).await {
+        warn!(?kind, ?err, "Cannot write hook output");
+    }
+
+    HookResult {
+        kind,
+        command: command.to_string(),
+        exit_code,
+        output_file,
+    }
+}
+
+async fn write_log(path: &Path, log: &[u8]) -> std::io::Result<()> {
+    let mut f = File::create(path).await?;
+    f.write_all(log).await?;
+    f.flush().await
+}
@@ -0,0 +1,831 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
+use models::report::{TestrunData, TestrunStatus, TestrunVisibilityStatus};
+use models::{SortOrder, Testrun, TestrunPage, TestrunQuery, TestrunSort};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+use tokio::fs::read_dir;
+use tracing::{info, warn};
+
+use crate::controller::{read_testrun, DATA_FILE};
+use crate::error::{self, Error};
+
+/// Bump this whenever the schema changes, the index is then rebuilt from disk.
+/// 2 added the date column, 3 tags, 4 sequence numbers, 5 the modification
+/// time of the data file and 6 baselines.
+const SCHEMA_VERSION: i32 = 6;
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 500;
+
+const SCHEMA: &str = "
+CREATE TABLE testruns (
+    name TEXT PRIMARY KEY,
+    creation_date TEXT NOT NULL,
+    date TEXT NOT NULL,
+    sequence INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    visibility_status TEXT NOT NULL,
+    simulation TEXT,
+    requests INTEGER NOT NULL,
+    errors INTEGER NOT NULL,
+    error_rate REAL NOT NULL,
+    mean INTEGER NOT NULL,
+    p95 INTEGER NOT NULL,
+    max INTEGER NOT NULL,
+    duration INTEGER NOT NULL,
+    baseline INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    modified INTEGER NOT NULL
+);
+CREATE TABLE params (
+    name TEXT NOT NULL REFERENCES testruns(name) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (name, key)
+);
+CREATE TABLE tags (
+    name TEXT NOT NULL REFERENCES testruns(name) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (name, tag)
+);
+CREATE INDEX params_key_value ON params (key, value);
+CREATE INDEX tags_tag ON tags (tag);
+CREATE INDEX testruns_date ON testruns (date);
+";
+
+/// Values which have to outlive the testruns, it is kept when the index is
+/// recreated.
+const META_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value INTEGER NOT NULL
+);
+";
+
+/// Highest sequence number handed out so far, so that numbers are not reused
+/// when the newest testruns are deleted.
+const SEQUENCE_KEY: &str = "sequence";
+
+/// SQLite index over the testruns in the result dir.
+///
+/// The result dir stays the source of truth, the index only keeps the data
+/// needed to list testruns without reading every run from disk.
+pub struct TestrunIndex {
+    conn: Mutex<Connection>,
+    sequence: AtomicU64,
+}
+
+impl TestrunIndex {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+
+        let version: i32 = conn.pragma_query_value(None, "user_version", |r| r.get(0))?;
+        if version != SCHEMA_VERSION {
+            info!(version, "Creating testrun index");
+            conn.execute_batch(
+                "DROP INDEX IF EXISTS params_key_value;
+                 DROP INDEX IF EXISTS tags_tag;
+                 DROP INDEX IF EXISTS testruns_date;
+                 DROP TABLE IF EXISTS params;
+                 DROP TABLE IF EXISTS tags;
+                 DROP TABLE IF EXISTS testruns;",
+            )?;
+            conn.execute_batch(SCHEMA)?;
+            conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        }
+        conn.execute_batch(META_SCHEMA)?;
+
+        let sequence: u64 = conn.query_row(
+            "SELECT MAX(COALESCE((SELECT MAX(sequence) FROM testruns), 0),
+                        COALESCE((SELECT value FROM meta WHERE key = ?1), 0))",
+            [SEQUENCE_KEY],
+            |r| r.get(0),
+        )?;
+
+        Ok(TestrunIndex {
+            conn: Mutex::new(conn),
+            sequence: AtomicU64::new(sequence),
+        })
+    }
+
+    /// Hands out the sequence number for a new testrun.
+    pub fn next_sequence(&self) -> u64 {
+        let conn = self.conn.lock().unwrap();
+        let sequence = self.sequence.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Err(err) = store_sequence(&conn, sequence) {
+            warn!(?err, "Cannot store sequence number");
+        }
+        sequence
+    }
+
+    /// Adds or replaces a testrun, `modified` is the time of its data file as
+    /// returned by `data_modified`.
+    pub fn upsert(&self, testrun: &Testrun, modified: i64) -> error::Result<()> {
+        let default_data = TestrunData::default();
+        let data = testrun.data.as_ref().unwrap_or(&default_data);
+        let summary = data
+            .statistics
+            .as_ref()
+            .map(|s| s.summary())
+            .unwrap_or_default();
+
+        self.sequence.fetch_max(data.sequence, Ordering::SeqCst);
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        store_sequence(&tx, data.sequence)?;
+        tx.execute("DELETE FROM testruns WHERE name = ?1", [&testrun.name])?;
+        tx.execute(
+            "INSERT INTO testruns (name, creation_date, date, sequence, status, visibility_status,
+                simulation, requests, errors, error_rate, mean, p95, max, duration, baseline, data,
+                modified)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+            params![
+                testrun.name,
+                testrun.creation_date,
+                index_date(&data.start_date().unwrap_or_else(|| {
+                    DateTime::parse_from_rfc3339(&testrun.creation_date)
+                        .map(|d| d.with_timezone(&Utc))
+                        .unwrap_or_default()
+                })),
+                data.sequence,
+                status_name(&data.status),
+                visibility_name(&data.visibility_status),
+                data.statistics.as_ref().map(|s| s.name.clone()),
+                summary.requests,
+                summary.errors,
+                summary.error_rate,
+                summary.mean,
+                summary.p95,
+                summary.max,
+                summary.duration,
+                data.baseline,
+                serde_json::to_string(data)?,
+                modified,
+            ],
+        )?;
+        for (key, value) in &data.custom_params {
+            tx.execute(
+                "INSERT INTO params (name, key, value) VALUES (?1, ?2, ?3)",
+                params![testrun.name, key, value],
+            )?;
+        }
+        for tag in &data.tags {
+            tx.execute(
+                "INSERT INTO tags (name, tag) VALUES (?1, ?2)",
+                params![testrun.name, tag],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn remove(&self, name: &str) -> error::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM testruns WHERE name = ?1", [name])?;
+        Ok(())
+    }
+
+    /// The testruns matching `query`. Running runs are always listed first,
+    /// followed by highlighted ones.
+    pub fn query(&self, query: &TestrunQuery) -> error::Result<TestrunPage> {
+        let order = query.order.unwrap_or_default();
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let direction = match order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+        let sort_value = match query.sort.unwrap_or_default() {
+            TestrunSort::Date => "date",
+            TestrunSort::Simulation => "COALESCE(simulation, '')",
+            TestrunSort::Requests => "requests",
+            TestrunSort::Errors => "errors",
+            TestrunSort::ErrorRate => "error_rate",
+            TestrunSort::Mean => "mean",
+            TestrunSort::P95 => "p95",
+            TestrunSort::Max => "max",
+            TestrunSort::Duration => "duration",
+        };
+        let pinned = format!(
+            "(CASE WHEN status = '{}' THEN 2 WHEN visibility_status = '{}' THEN 1 ELSE 0 END)",
+            status_name(&TestrunStatus::Running),
+            visibility_name(&TestrunVisibilityStatus::Highlighted)
+        );
+
+        let mut conditions: Vec<String> = vec![];
+        let mut values: Vec<Value> = vec![];
+
+        match query.visibility {
+            Some(ref v) => {
+                conditions.push("visibility_status = ?".into());
+                values.push(Value::Text(visibility_name(v)));
+            }
+            None if !query.include_hidden => {
+                conditions.push("visibility_status != ?".into());
+                values.push(Value::Text(visibility_name(
+                    &TestrunVisibilityStatus::Hidden,
+                )));
+            }
+            None => (),
+        }
+        if let Some(ref from) = query.from {
+            conditions.push("date >= ?".into());
+            values.push(Value::Text(index_date(from)));
+        }
+        if let Some(ref to) = query.to {
+            conditions.push("date <= ?".into());
+            values.push(Value::Text(index_date(to)));
+        }
+        if let Some(ref status) = query.status {
+            conditions.push("status = ?".into());
+            values.push(Value::Text(status_name(status)));
+        }
+        if let Some(ref simulation) = query.simulation {
+            // The name is matched literally, not as pattern.
+            let escaped = simulation
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            conditions.push("simulation LIKE '%' || ? || '%' ESCAPE '\\'".into());
+            values.push(Value::Text(escaped));
+        }
+        for (key, value) in parse_params(query.params.as_deref().unwrap_or_default()) {
+            conditions.push(
+                "EXISTS (SELECT 1 FROM params p
+                    WHERE p.name = testruns.name AND p.key = ? AND p.value = ?)"
+                    .into(),
+            );
+            values.push(Value::Text(key));
+            values.push(Value::Text(value));
+        }
+        for tag in parse_tags(query.tags.as_deref().unwrap_or_default()) {
+            conditions.push(
+                "EXISTS (SELECT 1 FROM tags t WHERE t.name = testruns.name AND t.tag = ?)".into(),
+            );
+            values.push(Value::Text(tag));
+        }
+        if let Some(ref cursor) = query.cursor {
+            let cursor =
+                Cursor::decode(cursor).ok_or_else(|| Error::BadRequest("Invalid cursor".into()))?;
+            let cmp = match order {
+                SortOrder::Asc => ">",
+                SortOrder::Desc => "<",
+            };
+            conditions.push(format!(
+                "({pinned} < ? OR ({pinned} = ? AND ({sort_value} {cmp} ?
+                    OR ({sort_value} = ? AND name {cmp} ?))))"
+            ));
+            values.push(Value::Integer(cursor.pinned));
+            values.push(Value::Integer(cursor.pinned));
+            values.push(cursor.value.clone());
+            values.push(cursor.value);
+            values.push(Value::Text(cursor.name));
+        }
+
+        let sql = format!(
+            "SELECT name, creation_date, data, {pinned}, {sort_value} FROM testruns
+             WHERE {}
+             ORDER BY {pinned} DESC, {sort_value} {direction}, name {direction}
+             LIMIT {}",
+            if conditions.is_empty() {
+                "1".to_string()
+            } else {
+                conditions.join(" AND ")
+            },
+            limit + 1
+        );
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(values), |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+                r.get::<_, i64>(3)?,
+                r.get::<_, Value>(4)?,
+            ))
+        })?;
+
+        let mut testruns = vec![];
+        let mut next_cursor = None;
+        let mut last_pinned = 0;
+        let mut last_value = Value::Null;
+        for row in rows {
+            let (name, creation_date, data, pinned, value) = row?;
+            if testruns.len() == limit as usize {
+                // There is one more row than requested, so the last one
+                // returned marks the start of the next page.
+                next_cursor = testruns.last().map(|t: &Testrun| {
+                    Cursor {
+                        pinned: last_pinned,
+                        value: last_value.clone(),
+                        name: t.name.clone(),
+                    }
+                    .encode()
+                });
+                break;
+            }
+            last_pinned = pinned;
+            last_value = value;
+            testruns.push(Testrun {
+                creation_date,
+                name,
+                progress: None,
+                data: Some(serde_json::from_str(&data)?),
+            });
+        }
+
+        Ok(TestrunPage {
+            testruns,
+            next_cursor,
+        })
+    }
+
+    /// All indexed testruns, newest first.
+    pub fn all(&self) -> error::Result<Vec<Testrun>> {
+        self.select(
+            "SELECT name, creation_date, data FROM testruns ORDER BY date DESC, sequence DESC",
+            vec![],
+        )
+    }
+
+    /// The finished, not hidden runs of a simulation, oldest first.
+    pub fn simulation_runs(&self, simulation: &str) -> error::Result<Vec<Testrun>> {
+        self.select(
+            "SELECT name, creation_date, data FROM testruns
+             WHERE simulation = ? AND status = ? AND visibility_status != ?
+             ORDER BY date ASC, sequence ASC",
+            vec![
+                Value::Text(simulation.to_string()),
+                Value::Text(status_name(&TestrunStatus::Done)),
+                Value::Text(visibility_name(&TestrunVisibilityStatus::Hidden)),
+            ],
+        )
+    }
+
+    /// The runs of a simulation marked as baseline, newest first.
+    pub fn baselines(&self, simulation: &str) -> error::Result<Vec<Testrun>> {
+        self.select(
+            "SELECT name, creation_date, data FROM testruns
+             WHERE simulation = ? AND baseline
+             ORDER BY date DESC, sequence DESC",
+            vec![Value::Text(simulation.to_string())],
+        )
+    }
+
+    /// The names of all simulations with runs, sorted.
+    pub fn simulations(&self) -> error::Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT simulation FROM testruns
+             WHERE simulation IS NOT NULL ORDER BY simulation",
+        )?;
+        let rows = stmt.query_map([], |r| r.get::<_, String>(0))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn select(&self, sql: &str, values: Vec<Value>) -> error::Result<Vec<Testrun>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(params_from_iter(values), |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+            ))
+        })?;
+
+        let mut testruns = vec![];
+        for row in rows {
+            let (name, creation_date, data) = row?;
+            testruns.push(Testrun {
+                creation_date,
+                name,
+                progress: None,
+                data: Some(serde_json::from_str(&data)?),
+            });
+        }
+        Ok(testruns)
+    }
+
+    /// The names of the indexed testruns with the time of their data file.
+    fn modified_times(&self) -> error::Result<HashMap<String, i64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT name, modified FROM testruns")?;
+        let names = stmt
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(names)
+    }
+
+    fn clear(&self) -> error::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM testruns", [])?;
+        Ok(())
+    }
+
+    /// Brings the index in line with the result dir: runs missing in the
+    /// index or whose data file changed are read from disk, entries without
+    /// a directory are dropped.
+    pub async fn sync(&self, result_dir: &Path) -> error::Result<()> {
+        let mut known = self.modified_times()?;
+
+        if let Ok(mut dir) = read_dir(result_dir).await {
+            while let Ok(Some(e)) = dir.next_entry().await {
+                if !e.path().is_dir() {
+                    continue;
+                }
+                let name = e.file_name().to_string_lossy().to_string();
+                let modified = data_modified(&e.path());
+                match known.remove(&name) {
+                    Some(indexed) if indexed == modified => continue,
+                    Some(_) => info!(name, "Refreshing changed testrun in index"),
+                    None => info!(name, "Adding testrun to index"),
+                }
+                if let Some(testrun) = read_testrun(&e.path()).await {
+                    self.upsert(&testrun, modified)?;
+                }
+            }
+        }
+
+        for name in known.into_keys() {
+            info!(name, "Removing vanished testrun from index");
+            self.remove(&name)?;
+        }
+
+        Ok(())
+    }
+
+    /// Drops the whole index and reads all testruns from disk again.
+    pub async fn rebuild(&self, result_dir: &Path) -> error::Result<()> {
+        self.clear()?;
+        self.sync(result_dir).await
+    }
+}
+
+/// Raises the stored highest sequence number to `sequence`.
+fn store_sequence(conn: &Connection, sequence: u64) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO meta (key, value) VALUES (?1, ?2)
+         ON CONFLICT (key) DO UPDATE SET value = MAX(value, excluded.value)",
+        params![SEQUENCE_KEY, sequence],
+    )?;
+    Ok(())
+}
+
+/// Position in a sorted list of testruns, handed out to clients as an opaque
+/// string.
+struct Cursor {
+    pinned: i64,
+    value: Value,
+    name: String,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        let value = match self.value {
+            Value::Integer(i) => serde_json::Value::from(i),
+            Value::Real(f) => serde_json::Value::from(f),
+            Value::Text(ref t) => serde_json::Value::from(t.clone()),
+            _ => serde_json::Value::Null,
+        };
+        let json = serde_json::json!([self.pinned, value, self.name]);
+        URL_SAFE_NO_PAD.encode(json.to_string())
+    }
+
+    fn decode(cursor: &str) -> Option<Cursor> {
+        let json = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let json: serde_json::Value = serde_json::from_slice(&json).ok()?;
+        let value = match json.get(1)? {
+            serde_json::Value::Number(n) if n.is_i64() => Value::Integer(n.as_i64()?),
+            serde_json::Value::Number(n) => Value::Real(n.as_f64()?),
+            serde_json::Value::String(s) => Value::Text(s.clone()),
+            _ => Value::Null,
+        };
+        Some(Cursor {
+            pinned: json.get(0)?.as_i64()?,
+            value,
+            name: json.get(2)?.as_str()?.to_string(),
+        })
+    }
+}
+
+/// Parses `NAME:VALUE,NAME:VALUE` param filters.
+pub fn parse_params(params: &str) -> Vec<(String, String)> {
+    params
+        .split(',')
+        .filter_map(|p| p.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect()
+}
+
+fn parse_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Modification time of the data file of the testrun in `dir` in
+/// milliseconds, 0 if there is none.
+pub fn data_modified(dir: &Path) -> i64 {
+    std::fs::metadata(dir.join(DATA_FILE))
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Dates are stored in UTC with a fixed precision, so that they sort and
+/// compare as strings.
+fn index_date(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn status_name(status: &TestrunStatus) -> String {
+    format!("{:?}", status)
+}
+
+fn visibility_name(status: &TestrunVisibilityStatus) -> String {
+    format!("{:?}", status)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, remove_dir_all, write, File};
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    use chrono::DateTime;
+    use models::report::TestrunData;
+    use models::{SortOrder, Testrun, TestrunQuery, TestrunSort};
+    use tempfile::tempdir;
+
+    use crate::controller::DATA_FILE;
+    use crate::index::{TestrunIndex, MAX_LIMIT};
+    use crate::test_util::{finished, testrun};
+
+    fn write_run(result_dir: &Path, name: &str, notes: &str) {
+        let dir = result_dir.join(name);
+        create_dir_all(&dir).unwrap();
+        let data = TestrunData {
+            notes: notes.into(),
+            ..Default::default()
+        };
+        write(dir.join(DATA_FILE), serde_json::to_string(&data).unwrap()).unwrap();
+    }
+
+    /// Name and notes of the indexed runs, sorted by name.
+    fn indexed(index: &TestrunIndex) -> Vec<(String, String)> {
+        let mut runs: Vec<_> = index
+            .all()
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.name.clone(), t.data.unwrap().notes))
+            .collect();
+        runs.sort();
+        runs
+    }
+
+    /// A finished run started at `started` seconds, whose `home` requests
+    /// took the given milliseconds and were OK or not.
+    fn finished_run(
+        name: &str,
+        simulation: &str,
+        started: i64,
+        requests: &[(u64, bool)],
+    ) -> Testrun {
+        let requests: Vec<_> = requests.iter().map(|&(t, ok)| ("home", t, ok)).collect();
+        testrun(TestrunData {
+            started_at: DateTime::from_timestamp(started, 0),
+            ..finished(name, simulation, &requests)
+        })
+    }
+
+    fn names(index: &TestrunIndex, query: &TestrunQuery) -> Vec<String> {
+        let page = index.query(query).unwrap();
+        page.testruns.into_iter().map(|t| t.name).collect()
+    }
+
+    #[test]
+    fn query_sorts_by_every_key() {
+        let tmp = tempdir().unwrap();
+        let index = TestrunIndex::open(&tmp.path().join("index.sqlite")).unwrap();
+        // requests 2, errors 0, mean 200, max 300, duration 400
+        let a = finished_run("a", "Charlie", 300, &[(100, true), (300, true)]);
+        // requests 3, errors 2, mean 50, max 50, duration 150
+        let b = finished_run("b", "Alpha", 200, &[(50, true), (50, false), (50, false)]);
+        // requests 4, errors 1, mean 132, max 500, duration 530
+        let c = finished_run(
+            "c",
+            "Bravo",
+            100,
+            &[(500, false), (10, true), (10, true), (10, true)],
+        );
+        for run in [a, b, c] {
+            index.upsert(&run, 0).unwrap();
+        }
+
+        let ascending = [
+            (TestrunSort::Date, ["c", "b", "a"]),
+            (TestrunSort::Simulation, ["b", "c", "a"]),
+            (TestrunSort::Requests, ["a", "b", "c"]),
+            (TestrunSort::Errors, ["a", "c", "b"]),
+            (TestrunSort::ErrorRate, ["a", "c", "b"]),
+            (TestrunSort::Mean, ["b", "c", "a"]),
+            (TestrunSort::P95, ["b", "a", "c"]),
+            (TestrunSort::Max, ["b", "a", "c"]),
+            (TestrunSort::Duration, ["b", "a", "c"]),
+        ];
+        for (sort, expected) in ascending {
+            let mut query = TestrunQuery {
+                sort: Some(sort),
+                order: Some(SortOrder::Asc),
+                ..Default::default()
+            };
+            assert_eq!(names(&index, &query), expected, "{:?} ascending", sort);
+
+            query.order = Some(SortOrder::Desc);
+            let mut descending = expected.to_vec();
+            descending.reverse();
+            assert_eq!(names(&index, &query), descending, "{:?} descending", sort);
+        }
+    }
+
+    #[test]
+    fn cursor_pages_through_runs_with_equal_dates() {
+        let tmp = tempdir().unwrap();
+        let index = TestrunIndex::open(&tmp.path().join("index.sqlite")).unwrap();
+        for name in ["a", "b", "c", "d", "e"] {
+            index
+                .upsert(&finished_run(name, "Sim", 100, &[(10, true)]), 0)
+                .unwrap();
+        }
+
+        for (order, expected) in [
+            (SortOrder::Desc, ["e", "d", "c", "b", "a"]),
+            (SortOrder::Asc, ["a", "b", "c", "d", "e"]),
+        ] {
+            let mut query = TestrunQuery {
+                order: Some(order),
+                limit: Some(2),
+                ..Default::default()
+            };
+            let mut listed = vec![];
+            loop {
+                let page = index.query(&query).unwrap();
+                assert!(page.testruns.len() <= 2);
+                listed.extend(page.testruns.into_iter().map(|t| t.name));
+                match page.next_cursor {
+                    Some(cursor) => query.cursor = Some(cursor),
+                    None => break,
+                }
+            }
+            assert_eq!(listed, expected, "{:?}", order);
+        }
+    }
+
+    #[test]
+    fn simulation_filter_matches_literally() {
+        let tmp = tempdir().unwrap();
+        let index = TestrunIndex::open(&tmp.path().join("index.sqlite")).unwrap();
+        for (name, simulation) in [("a", "Load_Test"), ("b", "LoadXTest"), ("c", "100%Load")] {
+            index
+                .upsert(&finished_run(name, simulation, 100, &[(10, true)]), 0)
+                .unwrap();
+        }
+
+        let filtered = |simulation: &str| {
+            let query = TestrunQuery {
+                simulation: Some(simulation.into()),
+                ..Default::default()
+            };
+            names(&index, &query)
+        };
+        assert_eq!(filtered("load"), ["c", "b", "a"]);
+        assert_eq!(filtered("d_T"), ["a"]);
+        assert_eq!(filtered("0%L"), ["c"]);
+        assert_eq!(filtered("%"), ["c"]);
+    }
+
+    #[test]
+    fn query_limit_is_clamped() {
+        let tmp = tempdir().unwrap();
+        let index = TestrunIndex::open(&tmp.path().join("index.sqlite")).unwrap();
+        for i in 0..=MAX_LIMIT {
+            let run = finished_run(&format!("run-{:03}", i), "Sim", i as i64, &[(10, true)]);
+            index.upsert(&run, 0).unwrap();
+        }
+
+        let page = index
+            .query(&TestrunQuery {
+                limit: Some(MAX_LIMIT + 100),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.testruns.len(), MAX_LIMIT as usize);
+        assert!(page.next_cursor.is_some());
+
+        let page = index
+            .query(&TestrunQuery {
+                limit: Some(0),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.testruns.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn sync_follows_the_result_dir() {
+        let tmp = tempdir().unwrap();
+        let results = tmp.path().join("results");
+        write_run(&results, "a", "first");
+        write_run(&results, "b", "second");
+
+        let index = TestrunIndex::open(&tmp.path().join("index.sqlite")).unwrap();
+        index.sync(&results).await.unwrap();
+        assert_eq!(
+            indexed(&index),
+            vec![("a".into(), "first".into()), ("b".into(), "second".into())]
+        );
+
+        write_run(&results, "a", "edited on disk");
+        File::options()
+            .write(true)
+            .open(results.join("a").join(DATA_FILE))
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        remove_dir_all(results.join("b")).unwrap();
+        write_run(&results, "c", "third");
+
+        index.sync(&results).await.unwrap();
+        assert_eq!(
+            indexed(&index),
+            vec![
+                ("a".into(), "edited on disk".into()),
+                ("c".into(), "third".into())
+            ]
+        );
+
+        index.remove("a").unwrap();
+        assert_eq!(indexed(&index), vec![("c".into(), "third".into())]);
+    }
+
+    #[tokio::test]
+    async fn sequence_numbers_are_not_reused() {
+        let tmp = tempdir().unwrap();
+        let results = tmp.path().join("results");
+        let path = tmp.path().join("index.sqlite");
+
+        {
+            let index = TestrunIndex::open(&path).unwrap();
+            assert_eq!(index.next_sequence(), 1);
+            let run = finished_run("a", "Sim", 100, &[(10, true)]);
+            index.upsert(&run, 0).unwrap();
+            assert_eq!(index.next_sequence(), 2);
+            index.remove("a").unwrap();
+        }
+
+        let index = TestrunIndex::open(&path).unwrap();
+        index.rebuild(&results).await.unwrap();
+        assert_eq!(index.next_sequence(), 3);
+    }
+
+    #[tokio::test]
+    async fn index_is_kept_across_restarts_until_rebuilt() {
+        let tmp = tempdir().unwrap();
+        let results = tmp.path().join("results");
+        let path = tmp.path().join("index.sqlite");
+        write_run(&results, "a", "first");
+        write_run(&results, "b", "second");
+
+        {
+            let index = TestrunIndex::open(&path).unwrap();
+            index.sync(&results).await.unwrap();
+            index.remove("b").unwrap();
+        }
+
+        let index = TestrunIndex::open(&path).unwrap();
+        assert_eq!(indexed(&index), vec![("a".into(), "first".into())]);
+
+        index.rebuild(&results).await.unwrap();
+        assert_eq!(
+            indexed(&index),
+            vec![("a".into(), "first".into()), ("b".into(), "second".into())]
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use models::config::Threshold;
+use models::report::TestrunStatus;
+use models::Testrun;
+
+use crate::controller::{finished_testrun_dir, read_testrun};
+use crate::error::{self, Error};
+use crate::AppState;
+
+struct Testcase {
+    name: String,
+    failures: Vec<(String, String)>,
+}
+
+fn escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&apos;"),
+            c if c.is_control() && c != '\n' && c != '\t' => (),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+fn testcases(testrun: &Testrun, thresholds: &[Threshold]) -> Vec<Testcase> {
+    let mut cases = vec![];
+    let data = testrun.data.as_ref();
+
+    if data.is_none_or(|d| d.status == TestrunStatus::Failed) {
+        cases.push(Testcase {
+            name: "simulation".into(),
+            failures: vec![("Simulation failed".into(), String::new())],
+        });
+    }
+
+    let Some(report) = data.and_then(|d| d.statistics.as_ref()) else {
+        return cases;
+    };
+
+    for stats in &report.request_stats {
+        let mut failures = vec![];
+
+        let errors = stats.error_count();
+        if errors > 0 {
+            let details = stats
+                .errors
+                .iter()
+                .map(|e| format!("{}: {}", e.count, e.name))
+                .collect::<Vec<_>>()
+                .join("\n");
+            failures.push((
+                format!("{} of {} requests failed", errors, stats.count),
+                details,
+            ));
+        }
+
+        for v in stats.violations(thresholds) {
+            failures.push((
+                format!(
+                    "{} is {} but must not exceed {}",
+                    v.threshold.metric.name(),
+                    v.value,
+                    v.threshold.max
+                ),
+                String::new(),
+            ));
+        }
+
+        let regressions = data
+            .and_then(|d| d.regression.as_ref())
+            .into_iter()
+            .flat_map(|r| r.deltas.iter().map(move |d| (r, d)))
+            .filter(|(_, d)| d.regression && d.request == stats.name);
+        for (r, d) in regressions {
+            failures.push((
+                format!(
+                    "{} is {} compared to {} of baseline #{}",
+                    d.metric.name(),
+                    d.value,
+                    d.baseline,
+                    r.baseline_sequence
+                ),
+                String::new(),
+            ));
+        }
+
+        cases.push(Testcase {
+            name: stats.name.clone(),
+            failures,
+        });
+    }
+
+    cases
+}
+
+/// Renders a testrun as JUnit XML, with one testcase per request. KO
+/// responses, exceeded thresholds and regressions are reported as failures.
+pub fn junit_report(testrun: &Testrun, thresholds: &[Threshold]) -> String {
+    let data = testrun.data.as_ref();
+    let suite = data
+        .and_then(|d| d.statistics.as_ref())
+        .map(|s| s.name.clone())
+        .unwrap_or_else(|| testrun.name.clone());
+    let summary = data
+        .and_then(|d| d.statistics.as_ref())
+        .map(|s| s.summary())
+        .unwrap_or_default();
+    let timestamp = data
+        .and_then(|d| d.start_date())
+        .map(|d| d.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .unwrap_or_default();
+
+    let cases = testcases(testrun, thresholds);
+    let failed = cases.iter().filter(|c| !c.failures.is_empty()).count();
+    let time = summary.duration as f64 / 1000.0;
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"waterpistol\" tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{}\">",
+        cases.len(),
+        failed,
+        time
+    );
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{}\" id=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"0\" time=\"{}\" timestamp=\"{}\">",
+        escape(&suite),
+        escape(testrun.id()),
+        cases.len(),
+        failed,
+        time,
+        timestamp
+    );
+    for case in cases {
+        let _ = write!(
+            xml,
+            "    <testcase classname=\"{}\" name=\"{}\"",
+            escape(&suite),
+            escape(&case.name)
+        );
+        if case.failures.is_empty() {
+            xml.push_str("/>\n");
+            continue;
+        }
+        xml.push_str(">\n");
+        for (message, details) in case.failures {
+            let _ = writeln!(
+                xml,
+                "      <failure message=\"{}\">{}</failure>",
+                escape(&message),
+                escape(&details)
+            );
+        }
+        xml.push_str("    </testcase>\n");
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// JUnit XML report of a finished testrun, checked against the configured
+/// thresholds.
+pub async fn get_junit(
+    Path(name): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> error::Result<Response> {
+    let testrun = read_testrun(&finished_testrun_dir(&state, &name)?)
+        .await
+        .ok_or(Error::NotFound)?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "application/xml")],
+        junit_report(&testrun, &state.config().thresholds),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use models::config::{Threshold, ThresholdMetric};
+    use models::report::{TestrunData, TestrunStatus};
+
+    use crate::junit::{escape, junit_report, testcases};
+    use crate::test_util::{finished, testrun};
+
+    #[test]
+    fn xml_special_characters_are_escaped() {
+        assert_eq!(
+            escape("<a href=\"x\">Tom & Jerry's</a>\u{7}"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;"
+        );
+
+        let run = testrun(finished(
+            "run",
+            "Sim<&>",
+            &[("search \"a&b\" <'c'>", 10, false)],
+        ));
+        let xml = junit_report(&run, &[]);
+
+        assert!(xml.contains(
+            r#"<testcase classname="Sim&lt;&amp;&gt;" name="search &quot;a&amp;b&quot; &lt;&apos;c&apos;&gt;">"#
+        ), "{}", xml);
+        assert!(!xml.contains("a&b"), "{}", xml);
+    }
+
+    #[test]
+    fn requests_above_thresholds_fail() {
+        let run = testrun(finished(
+            "run",
+            "Sim",
+            &[("home", 100, true), ("login", 10, true)],
+        ));
+        let thresholds = [Threshold {
+            request: Some("home".into()),
+            metric: ThresholdMetric::Max,
+            max: 50.0,
+        }];
+
+        let cases = testcases(&run, &thresholds);
+
+        assert_eq!(cases.len(), 2);
+        let failures = |name: &str| {
+            let case = cases.iter().find(|c| c.name == name).unwrap();
+            case.failures.clone()
+        };
+        assert_eq!(
+            failures("home"),
+            vec![(
+                "max is 100 but must not exceed 50".to_string(),
+                String::new()
+            )]
+        );
+        assert!(failures("login").is_empty());
+
+        let xml = junit_report(&run, &thresholds);
+        assert!(xml.contains(r#"tests="2" failures="1""#), "{}", xml);
+        assert!(
+            xml.contains(r#"<testcase classname="Sim" name="login"/>"#),
+            "{}",
+            xml
+        );
+    }
+
+    #[test]
+    fn failed_simulation_is_a_failed_testcase() {
+        let run = testrun(TestrunData {
+            id: "run".into(),
+            status: TestrunStatus::Failed,
+            ..Default::default()
+        });
+        let cases = testcases(&run, &[]);
+
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].name, "simulation");
+        assert_eq!(cases[0].failures[0].0, "Simulation failed");
+
+        let xml = junit_report(&run, &[]);
+        assert!(
+            xml.contains(r#"<testsuite name="run" id="run" tests="1" failures="1""#),
+            "{}",
+            xml
+        );
+        assert!(
+            xml.contains(r#"<failure message="Simulation failed"></failure>"#),
+            "{}",
+            xml
+        );
+    }
+}
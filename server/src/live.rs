@@ -0,0 +1,210 @@
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::io::SeekFrom;
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::{Path, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::stream::{self, Stream};
+use models::LiveStats;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::time::sleep;
+
+use crate::controller::{is_running, run_dir};
+use crate::error;
+use crate::gatling::find_result_dir;
+use crate::AppState;
+
+/// How often the live figures of a run are sent.
+pub const LIVE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The rate and percentile are taken over the responses of this many
+/// milliseconds.
+const LIVE_WINDOW: u64 = 10_000;
+
+/// The part of a `simulation.log` read so far, reduced to the live figures.
+#[derive(Default)]
+struct LogTail {
+    file: Option<PathBuf>,
+    offset: u64,
+    /// Start of a line which is not completely written yet.
+    partial: Vec<u8>,
+    start_time: Option<u64>,
+    last_time: u64,
+    started_users: u64,
+    ended_users: u64,
+    requests: u64,
+    errors: u64,
+    /// End and response time of the responses in the window.
+    recent: VecDeque<(u64, u64)>,
+}
+
+impl LogTail {
+    fn feed(&mut self, bytes: &[u8]) {
+        self.partial.extend_from_slice(bytes);
+        let Some(end) = self.partial.iter().rposition(|b| *b == b'\n') else {
+            return;
+        };
+        let complete: Vec<u8> = self.partial.drain(..=end).collect();
+        for line in String::from_utf8_lossy(&complete).lines() {
+            self.line(line);
+        }
+
+        let since = self.last_time.saturating_sub(LIVE_WINDOW);
+        self.recent.retain(|(end, _)| *end > since);
+    }
+
+    fn line(&mut self, line: &str) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let time = |i: usize| fields.get(i).and_then(|f| f.parse::<u64>().ok());
+        match fields[0] {
+            "RUN" => self.start_time = time(3),
+            "REQUEST" => {
+                let (Some(start), Some(end)) = (time(3), time(4)) else {
+                    return;
+                };
+                self.requests += 1;
+                if fields.get(5) != Some(&"OK") {
+                    self.errors += 1;
+                }
+                self.last_time = self.last_time.max(end);
+                self.recent.push_back((end, end.saturating_sub(start)));
+            }
+            "USER" => {
+                match fields.get(2) {
+                    Some(&"START") => self.started_users += 1,
+                    Some(&"END") => self.ended_users += 1,
+                    _ => (),
+                }
+                if let Some(timestamp) = time(3) {
+                    self.last_time = self.last_time.max(timestamp);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn stats(&self) -> LiveStats {
+        let elapsed = self
+            .start_time
+            .map(|s| self.last_time.saturating_sub(s))
+            .unwrap_or_default();
+        let window = elapsed.clamp(1000, LIVE_WINDOW) as f64 / 1000.0;
+
+        let mut times: Vec<u64> = self.recent.iter().map(|(_, t)| *t).collect();
+        times.sort_unstable();
+
+        LiveStats {
+            elapsed: elapsed / 1000,
+            started_users: self.started_users,
+            active_users: self.started_users.saturating_sub(self.ended_users),
+            requests: self.requests,
+            errors: self.errors,
+            rps: self.recent.len() as f64 / window,
+            p95: times.get(times.len() * 95 / 100).copied(),
+        }
+    }
+}
+
+/// Tails the `simulation.log` of the running testruns, so that every
+/// refresh only reads what was written since the last one.
+#[derive(Default)]
+pub struct LiveRuns {
+    tails: Mutex<HashMap<String, Arc<tokio::sync::Mutex<LogTail>>>>,
+}
+
+impl LiveRuns {
+    /// Live figures of the run in `dir`, `None` as long as there is no
+    /// simulation log.
+    pub async fn stats(&self, dir: &FsPath) -> Option<LiveStats> {
+        let name = dir.file_name()?.to_string_lossy().to_string();
+        let tail = self.tails.lock().unwrap().entry(name).or_default().clone();
+        let mut tail = tail.lock().await;
+
+        if tail.file.is_none() {
+            tail.file = find_result_dir(dir)
+                .await
+                .map(|d| d.join("simulation.log"))
+                .filter(|f| f.exists());
+        }
+        let mut file = File::open(tail.file.as_ref()?).await.ok()?;
+        file.seek(SeekFrom::Start(tail.offset)).await.ok()?;
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes).await.ok()?;
+
+        tail.offset += bytes.len() as u64;
+        tail.feed(&bytes);
+        Some(tail.stats())
+    }
+
+    /// Drops the state of a run which is not running anymore.
+    pub fn remove(&self, name: &str) {
+        self.tails.lock().unwrap().remove(name);
+    }
+}
+
+/// Stream of the live figures of a running testrun. Sends `progress` events
+/// with `LiveStats` and a final `finished` event once the run is over.
+pub async fn get_live(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> error::Result<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let id = id.trim_start_matches("running-").to_string();
+    run_dir(&state, &id)?;
+
+    let events = stream::unfold(
+        (state, id, true, false),
+        |(state, id, first, done)| async move {
+            if done {
+                return None;
+            }
+            if !first {
+                sleep(LIVE_INTERVAL).await;
+            }
+            loop {
+                let dir = run_dir(&state, &id).ok().filter(|d| is_running(d));
+                let Some(dir) = dir else {
+                    let event = Event::default().event("finished").data(id.clone());
+                    return Some((Ok(event), (state, id, false, true)));
+                };
+                if let Some(stats) = state.live.stats(&dir).await {
+                    let event = Event::default()
+                        .event("progress")
+                        .json_data(stats)
+                        .unwrap_or_default();
+                    return Some((Ok(event), (state, id, false, false)));
+                }
+                sleep(LIVE_INTERVAL).await;
+            }
+        },
+    );
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::live::LogTail;
+
+    #[test]
+    fn tail_reads_complete_lines_only() {
+        let mut tail = LogTail::default();
+        tail.feed(
+            b"RUN\tSim\tsim\t1000\tdesc\t3.9.2\nUSER\tJ\tSTART\t1000\nUSER\tJ\tSTART\t1100\n",
+        );
+        tail.feed(b"REQUEST\t\thome\t1100\t1300\tOK\t \nREQUEST\t\thome\t14");
+        tail.feed(b"00\t1500\tKO\tstatus 500\nUSER\tJ\tEND\t3000\n");
+
+        let stats = tail.stats();
+        assert_eq!(stats.elapsed, 2);
+        assert_eq!(stats.started_users, 2);
+        assert_eq!(stats.active_users, 1);
+        assert_eq!(stats.requests, 2);
+        assert_eq!(stats.errors, 1);
+        assert_eq!(stats.rps, 1.0);
+        assert_eq!(stats.p95, Some(200));
+    }
+}
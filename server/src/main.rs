@@ -1,40 +1,128 @@
+use agent::run_agent;
+use analyze::{analyze, AnalyzeOpt};
 use assets::static_handler;
-use axum::extract::State;
+use auth::{
+    authenticate, generate_token, get_me, hash_password, hash_token, post_login, post_logout,
+    Sessions,
+};
+use axum::extract::{DefaultBodyLimit, State};
 use axum::http::{header, StatusCode, Uri};
+use axum::middleware;
 use controller::{
-    get_config, get_status, get_testruns, get_testsuites, run_test, update_visibility_status, upload_archive
+    delete_testrun, export_testrun, get_config, get_run_status, get_status, get_testrun,
+    get_testruns, get_testsuites, import_testrun, merge_simulation_logs, rebuild_index, run_test,
+    update_testrun, upload_archive,
 };
+use distributed::{get_agents, get_testsuite_archive, poll_job, post_result, AgentRegistry};
+use events::{get_events, run_progress_events, watch_config, EventBus};
 use figment::providers::{Format, Serialized, Yaml};
 use figment::Figment;
+use index::TestrunIndex;
+use junit::get_junit;
+use live::{get_live, LiveRuns};
 use models::config::AppConfig;
+use retention::{get_retention_plan, run_retention};
+use stats::{get_comparison, get_testrun_stats};
 use tokio::io::AsyncReadExt;
+use trend::{get_trend, get_trend_simulations};
+use webhooks::{webhook_client, WEBHOOK_TIMEOUT};
 
-use axum::routing::{patch, post};
+use axum::routing::post;
 use axum::{response::IntoResponse, routing::get, Router};
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+use cli::{run_client, ClientCommand};
 
 use std::net::{IpAddr, Ipv6Addr, SocketAddr};
 use std::path::PathBuf;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use tokio::fs::File;
 use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
+use uuid::Uuid;
 
 use color_eyre::Result;
 
+pub mod agent;
+pub mod analyze;
+pub mod archive;
 pub mod assets;
+pub mod auth;
+pub mod baseline;
+pub mod cli;
 pub mod controller;
+pub mod distributed;
 pub mod error;
+pub mod events;
+pub mod gatling;
+pub mod hooks;
+pub mod index;
+pub mod junit;
+pub mod live;
+pub mod retention;
+pub mod stats;
+#[cfg(test)]
+mod test_util;
+pub mod trend;
+pub mod webhooks;
 
 // Setup the command line interface with clap.
 #[derive(Parser, Debug)]
-#[clap(name = "waterpistol", about = "A UI for running gatling tests!")]
+#[clap(
+    name = "waterpistol",
+    about = "A UI for running gatling tests!",
+    subcommand_negates_reqs = true
+)]
 struct Opt {
     /// set the log level
-    #[clap(short = 'l', long = "log", default_value = "debug")]
+    #[clap(short = 'l', long = "log", default_value = "debug", global = true)]
     log_level: String,
 
+    /// set the url of the waterpistol server the client commands talk to
+    #[clap(
+        long = "url",
+        env = "WATERPISTOL_URL",
+        default_value = "http://localhost:8080",
+        global = true
+    )]
+    url: String,
+
+    /// print the results of client commands as JSON
+    #[clap(long = "json", global = true)]
+    json: bool,
+
+    /// set the API token client commands and agents authenticate with
+    #[clap(long = "token", env = "WATERPISTOL_TOKEN", global = true)]
+    token: Option<String>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// without a command the server is run with these options
+    #[clap(flatten)]
+    serve: Option<ServeOpt>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// run the waterpistol server, or an injector agent with --controller
+    Serve(ServeOpt),
+
+    /// analyze a simulation.log locally, exits with 1 if it did not pass
+    Analyze(AnalyzeOpt),
+
+    /// read a password from stdin and print its hash for waterpistol.yml
+    HashPassword,
+
+    /// generate an API token and print it with its hash for waterpistol.yml
+    CreateToken,
+
+    #[clap(flatten)]
+    Client(ClientCommand),
+}
+
+#[derive(Args, Debug)]
+struct ServeOpt {
     /// set the listen addr
     #[clap(short = 'a', long = "addr", default_value = "::1")]
     addr: String,
@@ -46,6 +134,14 @@ struct Opt {
     /// set the gatling dir to use
     #[clap(long = "data-dir")]
     data_dir: PathBuf,
+
+    /// run as injector agent of the waterpistol controller at this url
+    #[clap(long = "controller")]
+    controller: Option<String>,
+
+    /// set the name this agent registers with at the controller
+    #[clap(long = "agent-name")]
+    agent_name: Option<String>,
 }
 
 const TESTSUITE_NAME: &str = "main"; // TODO: Make this configurable...
@@ -53,7 +149,41 @@ const TESTSUITE_NAME: &str = "main"; // TODO: Make this configurable...
 pub struct AppState {
     pub data_dir: PathBuf,
     pub result_dir: PathBuf,
-    pub app_config: AppConfig,
+    app_config: RwLock<Arc<AppConfig>>,
+    pub agents: AgentRegistry,
+    pub index: TestrunIndex,
+    pub live: LiveRuns,
+    pub events: EventBus,
+    pub sessions: Sessions,
+    pub webhook_client: reqwest::Client,
+}
+
+impl AppState {
+    /// The current configuration, it is reloaded when `waterpistol.yml`
+    /// changes.
+    pub fn config(&self) -> Arc<AppConfig> {
+        self.app_config.read().unwrap().clone()
+    }
+
+    fn set_config(&self, config: AppConfig) {
+        *self.app_config.write().unwrap() = Arc::new(config);
+    }
+
+    /// A state with an empty result dir in `data_dir`, for tests.
+    #[cfg(test)]
+    fn for_tests(data_dir: &std::path::Path, config: AppConfig) -> Arc<AppState> {
+        Arc::new(AppState {
+            data_dir: data_dir.to_path_buf(),
+            result_dir: data_dir.join("results"),
+            app_config: RwLock::new(Arc::new(config)),
+            agents: AgentRegistry::default(),
+            index: TestrunIndex::open(&data_dir.join("index.sqlite")).unwrap(),
+            live: LiveRuns::default(),
+            events: EventBus::default(),
+            sessions: Sessions::default(),
+            webhook_client: webhook_client(WEBHOOK_TIMEOUT).unwrap(),
+        })
+    }
 }
 
 async fn simulations_handler(uri: Uri, State(state): State<Arc<AppState>>) -> impl IntoResponse {
@@ -78,14 +208,54 @@ async fn simulations_handler(uri: Uri, State(state): State<Arc<AppState>>) -> im
         let mime = mime_guess::from_path(&p).first_or_octet_stream();
         (StatusCode::OK, [(header::CONTENT_TYPE, mime.as_ref())], buf).into_response()
     } else {
-        return (StatusCode::NOT_FOUND, "Not found").into_response();
+        (StatusCode::NOT_FOUND, "Not found").into_response()
     }
 }
 
+/// Reads `waterpistol.yml` from the working directory.
+fn load_config() -> Result<AppConfig> {
+    Ok(Figment::from(Serialized::defaults(AppConfig::default()))
+        .merge(Yaml::file("waterpistol.yml"))
+        .extract()?)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let opt = Opt::parse();
 
+    let command = opt
+        .command
+        .or(opt.serve.map(Command::Serve))
+        .expect("clap requires --data-dir without a command");
+    let serve = match command {
+        Command::Serve(serve) => serve,
+        Command::Analyze(analyze_opt) => {
+            if !analyze(analyze_opt, opt.json, &load_config()?)? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Command::HashPassword => {
+            let mut password = String::new();
+            std::io::stdin().read_line(&mut password)?;
+            let password = password.trim_end_matches(['\r', '\n']);
+            println!("{}", hash_password(password)?);
+            return Ok(());
+        }
+        Command::CreateToken => {
+            let token = generate_token();
+            println!("token: {}", token);
+            println!("token_hash: {}", hash_token(&token));
+            return Ok(());
+        }
+        Command::Client(command) => {
+            if !run_client(&opt.url, opt.token.as_deref(), opt.json, command).await? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+    };
+
     // Setup logging & RUST_LOG from args
     if std::env::var("RUST_LOG").is_err() {
         std::env::set_var("RUST_LOG", format!("{},hyper=info,mio=info", opt.log_level))
@@ -93,34 +263,97 @@ async fn main() -> Result<()> {
     // enable console logging
     tracing_subscriber::fmt::init();
 
-    let config: AppConfig = Figment::from(Serialized::defaults(AppConfig::default()))
-        .merge(Yaml::file("waterpistol.yml"))
-        .extract()?;
+    let config = load_config()?;
 
-    let testsuite_dir = PathBuf::from(&opt.data_dir.canonicalize()?);
+    let testsuite_dir = PathBuf::from(&serve.data_dir.canonicalize()?);
+
+    if let Some(ref controller) = serve.controller {
+        let name = serve
+            .agent_name
+            .clone()
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        return run_agent(controller, opt.token.as_deref(), &name, &testsuite_dir).await;
+    }
+
+    let result_dir = testsuite_dir.join(TESTSUITE_NAME).join("target/gatling");
+
+    let index = TestrunIndex::open(&testsuite_dir.join("index.sqlite"))?;
+    index.sync(&result_dir).await?;
 
     let shared_state = Arc::new(AppState {
         data_dir: testsuite_dir.clone(),
-        result_dir: testsuite_dir.join(TESTSUITE_NAME).join("target/gatling"),
-        app_config: config,
+        result_dir,
+        app_config: RwLock::new(Arc::new(config)),
+        agents: AgentRegistry::default(),
+        index,
+        live: LiveRuns::default(),
+        events: EventBus::default(),
+        sessions: Sessions::default(),
+        webhook_client: webhook_client(WEBHOOK_TIMEOUT)?,
     });
 
+    if !shared_state.config().auth.is_enabled() {
+        log::warn!("No users or API tokens configured, everybody has full access");
+    }
+
+    tokio::spawn(run_retention(shared_state.clone()));
+    tokio::spawn(run_progress_events(shared_state.clone()));
+    tokio::spawn(watch_config(shared_state.clone()));
+
     let app = Router::new()
         .route("/api/testsuites", get(get_testsuites))
         .route("/api/testsuites/upload", post(upload_archive))
         .route("/api/testruns", get(get_testruns))
-        .route("/api/testruns/:name", patch(update_visibility_status))
+        .route(
+            "/api/testruns/merge",
+            post(merge_simulation_logs).layer(DefaultBodyLimit::disable()),
+        )
+        .route(
+            "/api/testruns/import",
+            post(import_testrun).layer(DefaultBodyLimit::disable()),
+        )
+        .route(
+            "/api/testruns/:name",
+            get(get_testrun)
+                .patch(update_testrun)
+                .delete(delete_testrun),
+        )
+        .route("/api/testruns/compare", get(get_comparison))
+        .route("/api/testruns/:name/export", get(export_testrun))
+        .route("/api/testruns/:name/stats", get(get_testrun_stats))
+        .route("/api/testruns/:name/junit.xml", get(get_junit))
+        .route("/api/testruns/:name/status", get(get_run_status))
+        .route("/api/testruns/:name/live", get(get_live))
+        .route("/api/trends", get(get_trend_simulations))
+        .route("/api/trends/:simulation", get(get_trend))
+        .route("/api/index/rebuild", post(rebuild_index))
+        .route("/api/retention/dry-run", get(get_retention_plan))
         .route("/api/run", post(run_test))
         .route("/api/config", get(get_config))
         .route("/api/status", get(get_status))
+        .route("/api/events", get(get_events))
+        .route("/api/agents", get(get_agents))
+        .route("/api/agents/:name/poll", post(poll_job))
+        .route(
+            "/api/agents/:name/jobs/:run_id",
+            post(post_result).layer(DefaultBodyLimit::disable()),
+        )
+        .route("/api/testsuites/:name/archive", get(get_testsuite_archive))
+        .route("/api/me", get(get_me))
         .route("/simulations/*path", get(simulations_handler))
+        .route_layer(middleware::from_fn_with_state(
+            shared_state.clone(),
+            authenticate,
+        ))
+        .route("/api/login", post(post_login))
+        .route("/api/logout", post(post_logout))
         .fallback_service(get(static_handler))
         .layer(ServiceBuilder::new().layer(TraceLayer::new_for_http()))
         .with_state(shared_state);
 
     let sock_addr = SocketAddr::from((
-        IpAddr::from_str(opt.addr.as_str()).unwrap_or(IpAddr::V6(Ipv6Addr::LOCALHOST)),
-        opt.port,
+        IpAddr::from_str(serve.addr.as_str()).unwrap_or(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+        serve.port,
     ));
 
     log::info!("listening on http://{}", sock_addr);
@@ -132,3 +365,29 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use crate::{Command, Opt};
+
+    #[test]
+    fn server_is_run_without_a_command() {
+        let opt = Opt::try_parse_from(["server", "--data-dir", "data", "-p", "8081"]).unwrap();
+        assert!(opt.command.is_none());
+        let serve = opt.serve.unwrap();
+        assert_eq!(serve.data_dir.to_str(), Some("data"));
+        assert_eq!(serve.port, 8081);
+
+        let opt = Opt::try_parse_from(["server", "--log", "info", "serve", "--data-dir", "data"])
+            .unwrap();
+        assert!(matches!(opt.command, Some(Command::Serve(_))));
+        assert_eq!(opt.log_level, "info");
+
+        let opt = Opt::try_parse_from(["server", "list"]).unwrap();
+        assert!(opt.serve.is_none());
+
+        assert!(Opt::try_parse_from(["server"]).is_err());
+    }
+}
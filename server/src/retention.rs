@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use models::config::RetentionConfig;
+use models::report::{TestrunStatus, TestrunVisibilityStatus};
+use models::{RetentionPlan, Testrun};
+use tokio::fs::{read_dir, remove_dir_all, remove_file};
+use tracing::{info, warn};
+
+use crate::controller::{remove_testrun, DATA_FILE};
+use crate::error;
+use crate::AppState;
+
+/// Works out which of `testruns` (newest first) the retention rules remove.
+/// `has_report` tells whether the HTML report of a run is still there.
+pub fn plan(
+    config: &RetentionConfig,
+    testruns: &[Testrun],
+    now: DateTime<Utc>,
+    has_report: impl Fn(&str) -> bool,
+) -> RetentionPlan {
+    let mut plan = RetentionPlan::default();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for testrun in testruns {
+        let Some(ref data) = testrun.data else {
+            continue;
+        };
+        if data.status == TestrunStatus::Running || testrun.name.starts_with("running-") {
+            continue;
+        }
+
+        let simulation = data
+            .statistics
+            .as_ref()
+            .map(|s| s.name.clone())
+            .unwrap_or_default();
+        // Highlighted runs and baselines are kept in addition to the last
+        // `keep_last` ones. Without its baseline, a preset gets no verdicts.
+        if data.visibility_status == TestrunVisibilityStatus::Highlighted || data.baseline {
+            continue;
+        }
+        let position = seen.entry(simulation).or_default();
+        *position += 1;
+
+        let age_days = run_date(testrun).map(|d| (now - d).num_days());
+        let older_than = |days: Option<u32>| match (days, age_days) {
+            (Some(days), Some(age)) => age >= days as i64,
+            _ => false,
+        };
+
+        if config.keep_last.is_some_and(|n| *position > n) || older_than(config.max_age_days) {
+            plan.delete.push(testrun.name.clone());
+        } else if older_than(config.strip_reports_after_days) && has_report(&testrun.name) {
+            plan.strip_report.push(testrun.name.clone());
+        }
+    }
+
+    plan
+}
+
+fn run_date(testrun: &Testrun) -> Option<DateTime<Utc>> {
+    testrun
+        .data
+        .as_ref()
+        .and_then(|d| d.start_date())
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(&testrun.creation_date)
+                .ok()
+                .map(|d| d.with_timezone(&Utc))
+        })
+}
+
+async fn current_plan(state: &AppState) -> error::Result<RetentionPlan> {
+    let testruns = state.index.all()?;
+    Ok(plan(
+        &state.config().retention,
+        &testruns,
+        Utc::now(),
+        |name| state.result_dir.join(name).join("index.html").exists(),
+    ))
+}
+
+/// Removes everything but the data file from a testrun directory.
+async fn strip_report(dir: &Path) -> error::Result<()> {
+    let mut entries = read_dir(dir).await?;
+    while let Some(e) = entries.next_entry().await? {
+        if e.file_name() == DATA_FILE {
+            continue;
+        }
+        if e.file_type().await?.is_dir() {
+            remove_dir_all(e.path()).await?;
+        } else {
+            remove_file(e.path()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// A testrun which cannot be removed is logged and skipped, so that it does
+/// not keep the retention rules from cleaning up the others.
+async fn enforce(state: &AppState) -> error::Result<()> {
+    let plan = current_plan(state).await?;
+
+    for name in plan.delete {
+        info!(name, "Deleting testrun");
+        if let Err(err) = remove_testrun(state, &name).await {
+            warn!(name, ?err, "Cannot delete testrun");
+        }
+    }
+    for name in plan.strip_report {
+        info!(name, "Removing report of testrun");
+        if let Err(err) = strip_report(&state.result_dir.join(&name)).await {
+            warn!(name, ?err, "Cannot remove report of testrun");
+        }
+    }
+
+    Ok(())
+}
+
+/// Enforces the retention rules periodically, does nothing if no rule is
+/// configured.
+pub async fn run_retention(state: Arc<AppState>) {
+    let config = state.config().retention.clone();
+    if !config.is_enabled() {
+        return;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_minutes * 60));
+    loop {
+        interval.tick().await;
+        if let Err(err) = enforce(&state).await {
+            warn!(?err, "Cannot enforce retention rules");
+        }
+    }
+}
+
+/// What the retention rules would remove right now.
+pub async fn get_retention_plan(
+    State(state): State<Arc<AppState>>,
+) -> error::Result<Json<RetentionPlan>> {
+    Ok(Json(current_plan(&state).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+    use models::report::TestrunData;
+    use TestrunVisibilityStatus::{Hidden, Highlighted, Regular};
+
+    use super::*;
+    use crate::test_util::{finished, testrun};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    /// A finished run of `simulation` from `age` days ago.
+    fn run(name: &str, simulation: &str, age: i64, visibility: TestrunVisibilityStatus) -> Testrun {
+        testrun(TestrunData {
+            datum: Some(now() - Duration::days(age)),
+            visibility_status: visibility,
+            ..finished(name, simulation, &[])
+        })
+    }
+
+    #[test]
+    fn plan_respects_rules_and_highlights() {
+        let mut baseline = run("f", "Sim", 60, Regular);
+        baseline.data.as_mut().unwrap().baseline = true;
+        let testruns = vec![
+            run("a", "Sim", 1, Regular),
+            run("b", "Sim", 10, Regular),
+            run("c", "Sim", 20, Highlighted),
+            run("d", "Sim", 40, Hidden),
+            run("e", "Sim", 50, Highlighted),
+            baseline,
+        ];
+
+        let config = RetentionConfig {
+            max_age_days: Some(30),
+            strip_reports_after_days: Some(7),
+            ..Default::default()
+        };
+        let result = plan(&config, &testruns, now(), |name| name != "b");
+        assert_eq!(result.delete, vec!["d".to_string()]);
+        assert!(result.strip_report.is_empty());
+
+        let config = RetentionConfig {
+            keep_last: Some(2),
+            strip_reports_after_days: Some(7),
+            ..Default::default()
+        };
+        let result = plan(&config, &testruns, now(), |_| true);
+        assert_eq!(result.delete, vec!["d".to_string()]);
+        assert_eq!(result.strip_report, vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn keep_last_counts_runs_per_simulation_without_highlighted_ones() {
+        let running = testrun(TestrunData {
+            id: "running-x".into(),
+            status: TestrunStatus::Running,
+            ..Default::default()
+        });
+        let testruns = vec![
+            running,
+            run("highlighted", "First", 1, Highlighted),
+            run("first-new", "First", 2, Regular),
+            run("second-new", "Second", 3, Regular),
+            run("first-old", "First", 4, Regular),
+            run("second-old", "Second", 5, Regular),
+        ];
+
+        let config = RetentionConfig {
+            keep_last: Some(1),
+            ..Default::default()
+        };
+        let result = plan(&config, &testruns, now(), |_| true);
+        assert_eq!(
+            result.delete,
+            vec!["first-old".to_string(), "second-old".to_string()]
+        );
+        assert!(result.strip_report.is_empty());
+    }
+}
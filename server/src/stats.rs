@@ -0,0 +1,133 @@
+use std::io;
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use models::{comparison_rows, CompareQuery, ExportFormat, ExportQuery};
+use serde::Serialize;
+
+use crate::controller::{read_testrun, testrun_dir};
+use crate::error::{self, Error};
+use crate::AppState;
+
+pub fn to_csv<T: Serialize>(rows: Vec<T>) -> error::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for row in rows {
+        writer.serialize(row).map_err(io::Error::from)?;
+    }
+    Ok(writer.into_inner().map_err(|e| e.into_error())?)
+}
+
+/// Responds with `rows` as JSON array or as CSV download.
+fn export<T: Serialize>(
+    rows: Vec<T>,
+    format: ExportFormat,
+    file_name: &str,
+) -> error::Result<Response> {
+    match format {
+        ExportFormat::Json => Ok(Json(rows).into_response()),
+        ExportFormat::Csv => {
+            let csv = to_csv(rows)?;
+            Ok((
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, "text/csv".to_string()),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        format!("attachment; filename=\"{}.csv\"", file_name),
+                    ),
+                ],
+                csv,
+            )
+                .into_response())
+        }
+    }
+}
+
+/// Request and journey statistics of a testrun.
+pub async fn get_testrun_stats(
+    Path(name): Path<String>,
+    Query(query): Query<ExportQuery>,
+    State(state): State<Arc<AppState>>,
+) -> error::Result<Response> {
+    let testrun = read_testrun(&testrun_dir(&state, &name)?)
+        .await
+        .ok_or(Error::NotFound)?;
+    let rows = testrun
+        .data
+        .and_then(|d| d.statistics)
+        .map(|s| s.stats_rows())
+        .unwrap_or_default();
+
+    export(rows, query.format, &format!("{}-stats", name))
+}
+
+/// Every metric of every request in the given testruns.
+pub async fn get_comparison(
+    Query(query): Query<CompareQuery>,
+    State(state): State<Arc<AppState>>,
+) -> error::Result<Response> {
+    let mut testruns = vec![];
+    for name in query
+        .runs
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+    {
+        let testrun = read_testrun(&testrun_dir(&state, name)?)
+            .await
+            .ok_or(Error::NotFound)?;
+        testruns.push(testrun);
+    }
+    if testruns.is_empty() {
+        return Err(Error::BadRequest("No testruns given".into()));
+    }
+
+    export(comparison_rows(&testruns), query.format, "comparison")
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::header;
+    use chrono::{TimeZone, Utc};
+    use models::report::TestrunData;
+    use models::{comparison_rows, ExportFormat, Testrun};
+
+    use crate::stats::{export, to_csv};
+    use crate::test_util::{finished, testrun};
+
+    fn run(name: &str, request: &str, took: u64) -> Testrun {
+        testrun(TestrunData {
+            started_at: Some(Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()),
+            ..finished(name, "Sim", &[(request, took, true)])
+        })
+    }
+
+    #[test]
+    fn comparison_csv_lists_the_requests_of_each_run() {
+        let testruns = [run("a", "home", 100), run("b", "login", 40)];
+
+        let csv = to_csv(comparison_rows(&testruns)).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+
+        assert_eq!(lines[0], "request,run,started_at,metric,value");
+        assert_eq!(lines.len(), 1 + 2 * 8);
+        assert_eq!(lines[1], "home,a,2024-06-01T12:00:00Z,count,1");
+        assert_eq!(lines[2], "home,a,2024-06-01T12:00:00Z,errors,0");
+        assert_eq!(lines[4], "home,a,2024-06-01T12:00:00Z,max,100");
+        assert_eq!(lines[9], "login,b,2024-06-01T12:00:00Z,count,1");
+        assert_eq!(lines[12], "login,b,2024-06-01T12:00:00Z,max,40");
+        assert!(lines[1..9].iter().all(|l| l.starts_with("home,a,")));
+        assert!(lines[9..].iter().all(|l| l.starts_with("login,b,")));
+
+        let response = export(comparison_rows(&testruns), ExportFormat::Csv, "comparison").unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/csv");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"comparison.csv\""
+        );
+    }
+}
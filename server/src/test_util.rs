@@ -0,0 +1,43 @@
+//! Testruns shared by the tests of several modules.
+
+use models::report::{GatlingReport, TestrunData, TestrunStatus};
+use models::Testrun;
+
+/// Statistics of `simulation` whose requests, given as name, milliseconds
+/// taken and OK or not, ran one after the other.
+pub fn statistics(simulation: &str, requests: &[(&str, u64, bool)]) -> GatlingReport {
+    let mut log = format!("RUN\tS\ts\t1000\t{}\t3.9.2\n", simulation);
+    let mut time = 1000;
+    for (request, took, ok) in requests {
+        let status = if *ok { "OK" } else { "KO" };
+        log += &format!(
+            "REQUEST\t\t{}\t{}\t{}\t{}\t \n",
+            request,
+            time,
+            time + took,
+            status
+        );
+        time += took;
+    }
+    GatlingReport::from_file(&mut log.as_bytes()).unwrap()
+}
+
+/// Data of a finished run of `simulation`, see `statistics`.
+pub fn finished(id: &str, simulation: &str, requests: &[(&str, u64, bool)]) -> TestrunData {
+    TestrunData {
+        id: id.into(),
+        status: TestrunStatus::Done,
+        statistics: Some(statistics(simulation, requests)),
+        ..Default::default()
+    }
+}
+
+/// The testrun as listed, named after its id.
+pub fn testrun(data: TestrunData) -> Testrun {
+    Testrun {
+        creation_date: String::new(),
+        name: data.id.clone(),
+        progress: None,
+        data: Some(data),
+    }
+}
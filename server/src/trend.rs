@@ -0,0 +1,55 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use models::trend::{trend, Trend, TrendQuery, DEFAULT_TREND_WINDOW};
+
+use crate::error::{self, Error};
+use crate::index::parse_params;
+use crate::AppState;
+
+/// Names of the simulations which have runs.
+pub async fn get_trend_simulations(
+    State(state): State<Arc<AppState>>,
+) -> error::Result<Json<Vec<String>>> {
+    Ok(Json(state.index.simulations()?))
+}
+
+/// Figures of the requests of a simulation across its finished runs.
+pub async fn get_trend(
+    Path(simulation): Path<String>,
+    Query(query): Query<TrendQuery>,
+    State(state): State<Arc<AppState>>,
+) -> error::Result<Json<Trend>> {
+    let testruns = state.index.simulation_runs(&simulation)?;
+    if testruns.is_empty() {
+        return Err(Error::NotFound);
+    }
+
+    let presets: BTreeSet<BTreeMap<String, String>> = testruns
+        .iter()
+        .filter_map(|t| t.data.as_ref())
+        .map(|d| d.custom_params.clone().into_iter().collect())
+        .collect();
+
+    let params = parse_params(query.params.as_deref().unwrap_or_default());
+    let testruns: Vec<_> = testruns
+        .into_iter()
+        .filter(|t| {
+            t.data.as_ref().is_some_and(|d| {
+                params
+                    .iter()
+                    .all(|(k, v)| d.custom_params.get(k).is_some_and(|p| p == v))
+            })
+        })
+        .collect();
+
+    let mut trend = trend(
+        &simulation,
+        &testruns,
+        query.window.unwrap_or(DEFAULT_TREND_WINDOW),
+    );
+    trend.presets = presets.into_iter().collect();
+    Ok(Json(trend))
+}
@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use axum::http::header::CONTENT_TYPE;
+use hmac::{Hmac, Mac};
+use models::config::WebhookConfig;
+use models::report::TestrunData;
+use models::WebhookPayload;
+use reqwest::Client;
+use sha2::Sha256;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use crate::AppState;
+
+/// Header with the HMAC-SHA256 signature of the body, `sha256=<hex>`.
+pub const SIGNATURE_HEADER: &str = "X-Waterpistol-Signature";
+/// Header with the event the webhook is called for.
+pub const EVENT_HEADER: &str = "X-Waterpistol-Event";
+/// How long a webhook may take to answer before the call is retried.
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The client all webhooks are called with.
+pub fn webhook_client(timeout: Duration) -> reqwest::Result<Client> {
+    Client::builder().timeout(timeout).build()
+}
+
+/// The signature of `body` with the secret of a webhook.
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Calls the webhooks listening to a run which ended. The requests are sent
+/// in the background.
+pub fn notify(state: &AppState, data: &TestrunData) {
+    let config = state.config();
+    if config.webhooks.is_empty() {
+        return;
+    }
+
+    let verdict = data.verdict(&config.thresholds);
+    let report_url = state
+        .result_dir
+        .join(&data.id)
+        .join("index.html")
+        .exists()
+        .then(|| {
+            format!(
+                "{}/simulations/{}/index.html",
+                config
+                    .public_url
+                    .as_deref()
+                    .unwrap_or_default()
+                    .trim_end_matches('/'),
+                data.id
+            )
+        });
+
+    for webhook in &config.webhooks {
+        let Some(event) = webhook.event_for(data, &verdict) else {
+            continue;
+        };
+        let payload = WebhookPayload {
+            event,
+            id: data.id.clone(),
+            sequence: data.sequence,
+            simulation: data.statistics.as_ref().map(|s| s.name.clone()),
+            custom_params: data.custom_params.clone(),
+            summary: verdict.summary.clone(),
+            verdict: verdict.clone(),
+            report_url: report_url.clone(),
+        };
+        let webhook = webhook.clone();
+        let client = state.webhook_client.clone();
+        tokio::spawn(async move {
+            if deliver(&client, &webhook, &payload).await {
+                info!(url = webhook.url, id = payload.id, "Webhook called");
+            }
+        });
+    }
+}
+
+/// Posts the payload to the webhook, retrying with an exponential backoff.
+/// Returns whether the webhook accepted it.
+async fn deliver(client: &Client, webhook: &WebhookConfig, payload: &WebhookPayload) -> bool {
+    let body = serde_json::to_vec(payload).unwrap_or_default();
+
+    for attempt in 0..=webhook.retries {
+        if attempt > 0 {
+            let factor = 2u64.saturating_pow(attempt - 1);
+            sleep(Duration::from_secs(
+                webhook.backoff_seconds.saturating_mul(factor),
+            ))
+            .await;
+        }
+
+        let mut request = client
+            .post(&webhook.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, payload.event.name())
+            .body(body.clone());
+        if let Some(ref secret) = webhook.secret {
+            request = request.header(SIGNATURE_HEADER, signature(secret, &body));
+        }
+
+        match request.send().await {
+            Ok(resp) if resp.status().is_success() => return true,
+            Ok(resp) => {
+                warn!(url = webhook.url, attempt, status = %resp.status(), "Webhook failed")
+            }
+            Err(err) => warn!(url = webhook.url, attempt, ?err, "Cannot call webhook"),
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use axum::body::Bytes;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use models::config::{WebhookConfig, WebhookEvent};
+    use models::report::Verdict;
+    use models::WebhookPayload;
+    use tokio::net::TcpListener;
+    use tokio::time::sleep;
+
+    use crate::webhooks::{deliver, signature, webhook_client, SIGNATURE_HEADER};
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, Bytes)>>>;
+
+    /// Fails the first request, accepts the following ones.
+    async fn receive(
+        State(received): State<Received>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> StatusCode {
+        let mut received = received.lock().unwrap();
+        received.push((headers, body));
+        if received.len() == 1 {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    /// Never answers the first request, accepts the following ones.
+    async fn stall(State(received): State<Received>, headers: HeaderMap, body: Bytes) {
+        let first = {
+            let mut received = received.lock().unwrap();
+            received.push((headers, body));
+            received.len() == 1
+        };
+        if first {
+            sleep(Duration::from_secs(3600)).await;
+        }
+    }
+
+    /// Serves `/hook` and `/stall`, returns a webhook with retries for the
+    /// route.
+    async fn serve(received: &Received, route: &str) -> WebhookConfig {
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .route("/stall", post(stall))
+            .with_state(received.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        WebhookConfig {
+            url: format!("http://{}{}", addr, route),
+            events: vec![WebhookEvent::Finished],
+            secret: Some("s3cret".into()),
+            retries: 2,
+            backoff_seconds: 0,
+        }
+    }
+
+    fn payload() -> WebhookPayload {
+        WebhookPayload {
+            event: WebhookEvent::Finished,
+            id: "abc".into(),
+            sequence: 3,
+            simulation: Some("Sim".into()),
+            custom_params: Default::default(),
+            summary: None,
+            verdict: Verdict {
+                passed: true,
+                summary: None,
+                violations: vec![],
+                regression: None,
+            },
+            report_url: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn webhook_is_retried_and_signed() {
+        let received = Received::default();
+        let webhook = serve(&received, "/hook").await;
+        let payload = payload();
+        let client = webhook_client(Duration::from_secs(10)).unwrap();
+
+        assert!(deliver(&client, &webhook, &payload).await);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        let (headers, body) = &received[1];
+        assert_eq!(
+            headers[SIGNATURE_HEADER].to_str().unwrap(),
+            signature("s3cret", body)
+        );
+        let sent: WebhookPayload = serde_json::from_slice(body).unwrap();
+        assert_eq!(sent, payload);
+    }
+
+    #[tokio::test]
+    async fn stalled_webhook_is_retried() {
+        let received = Received::default();
+        let webhook = serve(&received, "/stall").await;
+        let client = webhook_client(Duration::from_millis(200)).unwrap();
+
+        assert!(deliver(&client, &webhook, &payload()).await);
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+}